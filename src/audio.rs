@@ -1,23 +1,36 @@
 use crate::loading::AudioAssets;
+use crate::settings::Settings;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
 
 pub struct InternalAudioPlugin;
 
+/// Channel the background music plays on, so its volume can be changed on its own.
+#[derive(Resource)]
+pub struct MusicChannel;
+
 // This plugin is responsible to control the game audio
 impl Plugin for InternalAudioPlugin
 {
     fn build(&self, app: &mut App)
     {
         app.add_plugins(AudioPlugin)
-            .add_systems(OnEnter(GameState::Playing), start_audio);
+            .add_audio_channel::<MusicChannel>()
+            .add_systems(OnEnter(GameState::Playing), start_audio)
+            .add_systems(Update, apply_music_volume.run_if(resource_changed::<Settings>)
+                                                    .run_if(in_state(GameState::Playing)));
     }
 }
 
-fn start_audio(audio_assets: Res<AudioAssets>, audio: Res<Audio>)
+fn start_audio(audio_assets: Res<AudioAssets>, music: Res<AudioChannel<MusicChannel>>, settings: Res<Settings>)
 {
-    let _ = audio.play(audio_assets.monty_moles.clone())
+    let _ = music.play(audio_assets.monty_moles.clone())
                         .looped()
-                        .with_volume(0.2);
+                        .with_volume(settings.music_volume);
+}
+
+fn apply_music_volume(music: Res<AudioChannel<MusicChannel>>, settings: Res<Settings>)
+{
+    music.set_volume(settings.music_volume);
 }
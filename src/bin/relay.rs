@@ -0,0 +1,184 @@
+//! Headless relay for online races. Clients join its lobby, any of them can start a race,
+//! and the relay hands everyone the same seed and passes live scores around.
+//!
+//! Run with `cargo run --bin relay [address]`, which listens on `0.0.0.0:7878` by default.
+
+use std::collections::HashMap;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use whack_a_key::online::{decode, encode, ClientMessage, RaceEntry, RelayMessage, DEFAULT_RELAY_PORT, MAX_DATAGRAM};
+
+/// Clients not heard from for this long are dropped from the lobby.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Racer
+{
+    id: u32,
+    name: String,
+    score: i32,
+    finished: bool,
+    last_heard: Instant,
+}
+
+struct Relay
+{
+    socket: UdpSocket,
+    racers: HashMap<SocketAddr, Racer>,
+    next_id: u32,
+}
+
+impl Relay
+{
+    fn handle(&mut self, from: SocketAddr, message: ClientMessage)
+    {
+        match message
+        {
+            ClientMessage::Join { name } =>
+            {
+                let is_new = !self.racers.contains_key(&from);
+                if is_new
+                {
+                    self.next_id += 1;
+                }
+                let id = self.next_id;
+                let racer = self.racers.entry(from).or_insert_with(|| Racer { id, name: name.clone(), score: 0, finished: true, last_heard: Instant::now() });
+                racer.name = name;
+                racer.last_heard = Instant::now();
+
+                // Answered every time, since the client keeps joining until the race starts and a datagram can get lost.
+                let id = racer.id;
+                self.send(from, &RelayMessage::Welcome { id });
+
+                if is_new
+                {
+                    println!("{} joined from {}", self.racers[&from].name, from);
+                    self.broadcast_lobby();
+                }
+            }
+            ClientMessage::Start { board, difficulty } =>
+            {
+                let seed = rand::random::<u64>();
+                println!("Starting a race on {} ({}) with seed {}", board, difficulty, seed);
+
+                for racer in self.racers.values_mut()
+                {
+                    racer.score = 0;
+                    racer.finished = false;
+                }
+                self.broadcast(&RelayMessage::Start { seed, board, difficulty });
+                self.broadcast_standings();
+            }
+            ClientMessage::Score { score, finished } =>
+            {
+                if let Some(racer) = self.racers.get_mut(&from)
+                {
+                    let changed = racer.score != score || racer.finished != finished;
+                    racer.score = score;
+                    racer.finished = finished;
+                    racer.last_heard = Instant::now();
+
+                    if changed
+                    {
+                        self.broadcast_standings();
+                    }
+                }
+            }
+            ClientMessage::Leave =>
+            {
+                if let Some(racer) = self.racers.remove(&from)
+                {
+                    println!("{} left", racer.name);
+                    self.broadcast_lobby();
+                }
+            }
+        }
+    }
+
+    fn drop_silent_clients(&mut self)
+    {
+        let num_racers = self.racers.len();
+        self.racers.retain(|_, racer| racer.last_heard.elapsed() < CLIENT_TIMEOUT);
+
+        if self.racers.len() != num_racers
+        {
+            println!("Dropped {} silent client(s)", num_racers - self.racers.len());
+            self.broadcast_lobby();
+        }
+    }
+
+    fn broadcast_lobby(&self)
+    {
+        let mut players: Vec<String> = self.racers.values().map(|racer| racer.name.clone()).collect();
+        players.sort();
+        self.broadcast(&RelayMessage::Lobby { players });
+    }
+
+    fn broadcast_standings(&self)
+    {
+        let mut entries: Vec<RaceEntry> = self.racers.values()
+                                            .map(|racer| RaceEntry { id: racer.id, name: racer.name.clone(), score: racer.score, finished: racer.finished })
+                                            .collect();
+        entries.sort_by(|a, b| b.score.cmp(&a.score).then(a.name.cmp(&b.name)));
+        self.broadcast(&RelayMessage::Standings { entries });
+    }
+
+    fn broadcast(&self, message: &RelayMessage)
+    {
+        for addr in self.racers.keys()
+        {
+            self.send(*addr, message);
+        }
+    }
+
+    fn send(&self, addr: SocketAddr, message: &RelayMessage)
+    {
+        let Some(bytes) = encode(message) else
+        {
+            eprintln!("Could not encode {:?}", message);
+            return;
+        };
+
+        if let Err(err) = self.socket.send_to(&bytes, addr)
+        {
+            eprintln!("Could not send to {}: {}", addr, err);
+        }
+    }
+}
+
+fn main()
+{
+    let address = std::env::args().nth(1).unwrap_or_else(|| format!("0.0.0.0:{}", DEFAULT_RELAY_PORT));
+    let socket = match UdpSocket::bind(&address)
+    {
+        Ok(socket) => socket,
+        Err(err) =>
+        {
+            eprintln!("Could not listen on {}: {}", address, err);
+            std::process::exit(1);
+        }
+    };
+    // Wake up now and then even when nobody is talking, to notice clients that went away.
+    socket.set_read_timeout(Some(Duration::from_secs(1))).expect("read timeout should be non-zero");
+    println!("Relay listening on {}", address);
+
+    let mut relay = Relay { socket, racers: HashMap::new(), next_id: 0 };
+    let mut buf = [0u8; MAX_DATAGRAM];
+    loop
+    {
+        match relay.socket.recv_from(&mut buf)
+        {
+            Ok((len, from)) =>
+            {
+                match decode::<ClientMessage>(&buf[..len])
+                {
+                    Some(message) => relay.handle(from, message),
+                    None => eprintln!("Ignoring a message from {} that could not be read", from),
+                }
+            }
+            Err(err) if matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+            Err(err) => eprintln!("Could not receive: {}", err),
+        }
+
+        relay.drop_silent_clients();
+    }
+}
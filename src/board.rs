@@ -0,0 +1,295 @@
+use crate::input::{gamepad_button_from_name, gamepad_button_label, InputBinding};
+use crate::keyboard::{key_code_from_name, slot_of_key, slot_position, to_upper_glyph, KeyBinding, KeyboardLayout};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::fmt;
+
+// =============================================
+// BOARD DEFINITION
+// =============================================
+
+/// A board of mole holes, loaded from a `.board.ron` file in `assets/boards`.
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct BoardDefinition
+{
+    pub name: String,
+    pub holes: Vec<HoleDefinition>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct HoleDefinition
+{
+    pub key: HoleKey,
+    /// Screen position of the hole. When omitted the hole sits on its key's
+    /// position on the keyboard, so it follows the selected layout. Gamepad buttons need one.
+    #[serde(default)]
+    pub position: Option<(f32, f32)>,
+    /// Text shown on the mole. Defaults to the glyph of its key.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Require shift to be held. Only used by `Physical` keys, glyphs know if they are shifted.
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub timing: HoleTiming,
+    /// Further keys that must be pressed together with `key` to whack the mole.
+    #[serde(default)]
+    pub chord: Vec<HoleKey>,
+}
+
+/// Which key a hole is bound to.
+#[derive(Deserialize, Clone, Debug)]
+pub enum HoleKey
+{
+    /// Whichever key types this glyph on the selected layout.
+    Glyph(char),
+    /// A fixed physical key, named like Bevy's `KeyCode` (e.g. "Numpad7" or "Space").
+    Physical(String),
+    /// A gamepad button, named like Bevy's `GamepadButton` (e.g. "South" or "DPadUp").
+    Button(String),
+}
+
+/// Per-hole overrides of how long (min, max seconds) a mole stays in each state.
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(default)]
+pub struct HoleTiming
+{
+    pub hidden: Option<(f32, f32)>,
+    pub up: Option<(f32, f32)>,
+    pub bonked: Option<(f32, f32)>,
+}
+
+impl HoleTiming
+{
+    /// Name of the first range that isn't a valid (min, max) with 0 < min <= max.
+    fn invalid_range(&self) -> Option<&'static str>
+    {
+        let ranges = [("hidden", self.hidden), ("up", self.up), ("bonked", self.bonked)];
+        return ranges.iter()
+                    .find(|(_, range)| range.is_some_and(|(min, max)| !(min > 0.0 && min <= max)))
+                    .map(|(name, _)| *name);
+    }
+}
+
+impl BoardDefinition
+{
+    /// Caught when the board loads rather than when a mole draws a time from a bad range mid-round.
+    fn validate(&self) -> Result<(), BoardLoaderError>
+    {
+        if let Some((hole, range)) = self.holes.iter().enumerate().find_map(|(idx, hole)| hole.timing.invalid_range().map(|range| (idx, range)))
+        {
+            return Err(BoardLoaderError::Timing { hole, range });
+        }
+
+        return Ok(());
+    }
+}
+
+/// A hole with its key and position worked out for a specific layout.
+pub struct ResolvedHole
+{
+    pub binding: InputBinding,
+    pub glyph: char,
+    /// Other keys of a chord, with their glyphs.
+    pub chord: Vec<(InputBinding, char)>,
+    pub label: String,
+    pub position: Vec2,
+    pub timing: HoleTiming,
+}
+
+/// A key worked out for a specific layout.
+struct ResolvedKey
+{
+    binding: InputBinding,
+    glyph: char,
+    /// Text to show for the key.
+    key_string: String,
+    slot: Option<(usize, usize)>,
+}
+
+impl HoleKey
+{
+    fn resolve(&self, shift: bool, layout: &KeyboardLayout) -> Option<ResolvedKey>
+    {
+        // Buttons are the same on every layout, and show their own label.
+        if let HoleKey::Button(name) = self
+        {
+            let button = gamepad_button_from_name(name)?;
+            let key_string = String::from(gamepad_button_label(button)?);
+            let glyph = key_string.chars().next()?;
+            return Some(ResolvedKey { binding: InputBinding::Button(button), glyph, key_string, slot: None });
+        }
+
+        let (binding, slot) = match self
+        {
+            HoleKey::Glyph(glyph) => (layout.binding_for_glyph(*glyph)?, layout.slot_of(*glyph)),
+            HoleKey::Physical(name) =>
+            {
+                let key_code = key_code_from_name(name)?;
+                (KeyBinding { key_code, shift }, slot_of_key(key_code))
+            }
+            HoleKey::Button(_) => return None,
+        };
+
+        let key_string = layout.key_code_to_string(binding.key_code, binding.shift);
+        let glyph = match self
+        {
+            HoleKey::Glyph(glyph) => to_upper_glyph(*glyph),
+            HoleKey::Physical(_) if binding.key_code == KeyCode::Space => ' ',
+            HoleKey::Physical(_) => key_string.chars().next()?,
+            HoleKey::Button(_) => return None,
+        };
+
+        return Some(ResolvedKey { binding: InputBinding::Key(binding), glyph, key_string, slot });
+    }
+}
+
+impl HoleDefinition
+{
+    pub fn resolve(&self, layout: &KeyboardLayout) -> Option<ResolvedHole>
+    {
+        let key = self.key.resolve(self.shift, layout)?;
+        let chord = self.chord.iter()
+                        .map(|chord_key| chord_key.resolve(false, layout))
+                        .collect::<Option<Vec<ResolvedKey>>>()?;
+
+        let position = match (self.position, key.slot)
+        {
+            (Some((x, y)), _) => Vec2::new(x, y),
+            (None, Some((row, col))) => slot_position(row, col),
+            (None, None) => return None,
+        };
+
+        let key_string = std::iter::once(&key).chain(chord.iter())
+                            .map(|resolved| resolved.key_string.as_str())
+                            .collect::<Vec<&str>>()
+                            .join("+");
+
+        return Some(ResolvedHole
+        {
+            binding: key.binding,
+            glyph: key.glyph,
+            chord: chord.iter().map(|resolved| (resolved.binding, resolved.glyph)).collect(),
+            label: self.label.clone().unwrap_or(key_string),
+            position,
+            timing: self.timing,
+        });
+    }
+}
+
+/// Index into `BoardAssets::boards` of the board the player picked.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct SelectedBoard
+{
+    pub idx: usize,
+}
+
+impl SelectedBoard
+{
+    pub fn cycle(&mut self, num_boards: usize)
+    {
+        self.idx = (self.idx + 1) % num_boards.max(1);
+    }
+}
+
+// =============================================
+// LOADER
+// =============================================
+#[derive(Default)]
+pub struct BoardLoader;
+
+#[derive(Debug)]
+pub enum BoardLoaderError
+{
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    /// A hole's timing range (named) is empty or not positive.
+    Timing { hole: usize, range: &'static str },
+}
+
+impl fmt::Display for BoardLoaderError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            BoardLoaderError::Io(err) => write!(f, "Could not read board: {}", err),
+            BoardLoaderError::Ron(err) => write!(f, "Could not parse board: {}", err),
+            BoardLoaderError::Timing { hole, range } => write!(f, "Hole {} has a {} timing whose min is not between 0 and max", hole, range),
+        }
+    }
+}
+
+impl std::error::Error for BoardLoaderError {}
+
+impl AssetLoader for BoardLoader
+{
+    type Asset = BoardDefinition;
+    type Settings = ();
+    type Error = BoardLoaderError;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), _load_context: &mut LoadContext<'_>)
+        -> Result<Self::Asset, Self::Error>
+    {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(BoardLoaderError::Io)?;
+
+        let board = ron::de::from_bytes::<BoardDefinition>(&bytes).map_err(BoardLoaderError::Ron)?;
+        board.validate()?;
+
+        return Ok(board);
+    }
+
+    fn extensions(&self) -> &[&str]
+    {
+        &["board.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn parse(holes: &str) -> BoardDefinition
+    {
+        return ron::from_str(&format!("(name: \"Test\", holes: [{}])", holes)).unwrap();
+    }
+
+    #[test]
+    fn boards_with_sensible_timings_load()
+    {
+        let board = parse("(key: Glyph('A')), (key: Physical(\"Numpad7\"), timing: (up: Some((1.0, 2.0)), bonked: Some((0.5, 0.5))))");
+        assert!(board.validate().is_ok());
+        assert_eq!(board.holes[1].timing.up, Some((1.0, 2.0)));
+    }
+
+    #[test]
+    fn backwards_ranges_are_rejected()
+    {
+        let board = parse("(key: Glyph('A')), (key: Glyph('S'), timing: (hidden: Some((3.0, 1.0))))");
+        assert!(matches!(board.validate(), Err(BoardLoaderError::Timing { hole: 1, range: "hidden" })));
+    }
+
+    #[test]
+    fn zero_and_negative_times_are_rejected()
+    {
+        let board = parse("(key: Glyph('A'), timing: (up: Some((0.0, 1.0))))");
+        assert!(matches!(board.validate(), Err(BoardLoaderError::Timing { hole: 0, range: "up" })));
+
+        let board = parse("(key: Glyph('A'), timing: (bonked: Some((-1.0, 1.0))))");
+        assert!(matches!(board.validate(), Err(BoardLoaderError::Timing { hole: 0, range: "bonked" })));
+    }
+
+    #[test]
+    fn holes_resolve_on_the_selected_layout()
+    {
+        let board = parse("(key: Glyph('A'), chord: [Glyph('S')])");
+        let hole = board.holes[0].resolve(&KeyboardLayout::azerty()).unwrap();
+        assert_eq!(hole.binding, InputBinding::Key(KeyBinding { key_code: KeyCode::KeyQ, shift: false }));
+        assert_eq!(hole.label, "A+S");
+        assert_eq!(hole.position, slot_position(1, 0));
+    }
+}
@@ -0,0 +1,488 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fmt;
+
+// =============================================
+// CURVES
+// =============================================
+
+/// How hard a round gets over time. Every value is looked up from the seconds
+/// since the round started, so the same curve always plays out the same.
+pub trait DifficultyCurve: Send + Sync
+{
+    fn name(&self) -> &str;
+
+    /// Most moles allowed to have their heads up at once.
+    fn max_moles_up(&self, elapsed_sec: f32) -> f32;
+
+    /// Multiplier on how long a mole stays up.
+    fn up_time_scale(&self, elapsed_sec: f32) -> f32;
+
+    /// Multiplier on how long a mole stays hidden before popping up.
+    fn hidden_time_scale(&self, elapsed_sec: f32) -> f32;
+
+    /// Multiplier on how long a bonked mole waits before it can come back.
+    fn bonk_cooldown_scale(&self, elapsed_sec: f32) -> f32;
+}
+
+/// Built-in curve: the number of moles rises towards `max_moles` and their timers
+/// shrink towards `min_scale`, both faster the higher `ramp` is.
+pub struct PresetCurve
+{
+    name: &'static str,
+    max_moles: f32,
+    ramp: f32,
+    min_scale: f32,
+}
+
+impl PresetCurve
+{
+    pub const EASY: PresetCurve = PresetCurve { name: "Easy", max_moles: 10.0, ramp: 0.005, min_scale: 0.7 };
+    pub const NORMAL: PresetCurve = PresetCurve { name: "Normal", max_moles: 18.0, ramp: 0.01, min_scale: 0.5 };
+    pub const HARD: PresetCurve = PresetCurve { name: "Hard", max_moles: 22.0, ramp: 0.02, min_scale: 0.4 };
+    pub const INSANE: PresetCurve = PresetCurve { name: "Insane", max_moles: 30.0, ramp: 0.04, min_scale: 0.3 };
+
+    fn time_scale(&self, elapsed_sec: f32) -> f32
+    {
+        return 1.0 / (elapsed_sec * self.ramp + 2.0) + self.min_scale;
+    }
+}
+
+impl DifficultyCurve for PresetCurve
+{
+    fn name(&self) -> &str
+    {
+        return self.name;
+    }
+
+    fn max_moles_up(&self, elapsed_sec: f32) -> f32
+    {
+        return (1.0 - (1.0 / (1.0 + elapsed_sec * self.ramp))) * self.max_moles;
+    }
+
+    fn up_time_scale(&self, elapsed_sec: f32) -> f32
+    {
+        return self.time_scale(elapsed_sec);
+    }
+
+    fn hidden_time_scale(&self, elapsed_sec: f32) -> f32
+    {
+        return self.time_scale(elapsed_sec);
+    }
+
+    fn bonk_cooldown_scale(&self, elapsed_sec: f32) -> f32
+    {
+        return self.time_scale(elapsed_sec);
+    }
+}
+
+/// A curve loaded from a `.curve.ron` file in `assets/difficulty`. Values are
+/// interpolated linearly between points and held flat past the first and last.
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct PiecewiseCurve
+{
+    pub name: String,
+    pub points: Vec<CurvePoint>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct CurvePoint
+{
+    /// Seconds since the round started.
+    pub time: f32,
+    pub max_moles: f32,
+    #[serde(default = "default_scale")]
+    pub up_time: f32,
+    #[serde(default = "default_scale")]
+    pub hidden_time: f32,
+    #[serde(default = "default_scale")]
+    pub bonk_cooldown: f32,
+}
+
+fn default_scale() -> f32
+{
+    return 1.0;
+}
+
+impl PiecewiseCurve
+{
+    fn sample(&self, elapsed_sec: f32, value: impl Fn(&CurvePoint) -> f32) -> f32
+    {
+        let Some(first) = self.points.first() else
+        {
+            return 1.0;
+        };
+
+        let mut prev = first;
+        for point in &self.points
+        {
+            if elapsed_sec < point.time
+            {
+                if point.time <= prev.time
+                {
+                    return value(point);
+                }
+
+                let t = (elapsed_sec - prev.time) / (point.time - prev.time);
+                return value(prev) + (value(point) - value(prev)) * t.clamp(0.0, 1.0);
+            }
+            prev = point;
+        }
+
+        return value(prev);
+    }
+
+    fn validate(&self) -> Result<(), CurveLoaderError>
+    {
+        // Scales end up as mole timer durations, which can't be negative.
+        if let Some(idx) = self.points.iter().position(|point| !(point.up_time > 0.0 && point.hidden_time > 0.0 && point.bonk_cooldown > 0.0))
+        {
+            return Err(CurveLoaderError::NonPositiveScale(idx));
+        }
+        if let Some(idx) = self.points.windows(2).position(|pair| pair[1].time < pair[0].time)
+        {
+            return Err(CurveLoaderError::Unsorted(idx + 1));
+        }
+
+        return Ok(());
+    }
+}
+
+impl DifficultyCurve for PiecewiseCurve
+{
+    fn name(&self) -> &str
+    {
+        return &self.name;
+    }
+
+    fn max_moles_up(&self, elapsed_sec: f32) -> f32
+    {
+        return self.sample(elapsed_sec, |point| point.max_moles);
+    }
+
+    fn up_time_scale(&self, elapsed_sec: f32) -> f32
+    {
+        return self.sample(elapsed_sec, |point| point.up_time);
+    }
+
+    fn hidden_time_scale(&self, elapsed_sec: f32) -> f32
+    {
+        return self.sample(elapsed_sec, |point| point.hidden_time);
+    }
+
+    fn bonk_cooldown_scale(&self, elapsed_sec: f32) -> f32
+    {
+        return self.sample(elapsed_sec, |point| point.bonk_cooldown);
+    }
+}
+
+// =============================================
+// ADAPTIVE
+// =============================================
+
+/// Number of recent moles the hit rate is worked out from.
+const ADAPTIVE_WINDOW: usize = 20;
+/// Outcomes needed before the difficulty starts moving.
+const ADAPTIVE_MIN_SAMPLES: usize = 5;
+/// How far one mole can move the difficulty level, per point of hit rate off target.
+const ADAPTIVE_RATE: f32 = 0.1;
+/// Roughly how long a mole stays up before any scaling, used to keep up-time above reaction time.
+const TYPICAL_UP_TIME: f32 = 3.5;
+
+/// Difficulty that follows the player instead of the clock. The level rises while the
+/// rolling hit rate is above `target` and falls while it is below, and moles never
+/// stay up for much less than twice the player's average reaction time.
+#[derive(Resource)]
+pub struct AdaptiveDifficulty
+{
+    target: Option<f32>,
+    /// 0 is gentlest, 1 is hardest.
+    level: f32,
+    outcomes: VecDeque<bool>,
+    reaction_times: VecDeque<f32>,
+}
+
+impl Default for AdaptiveDifficulty
+{
+    fn default() -> Self
+    {
+        Self
+        {
+            target: None,
+            level: 0.2,
+            outcomes: VecDeque::new(),
+            reaction_times: VecDeque::new(),
+        }
+    }
+}
+
+impl AdaptiveDifficulty
+{
+    /// Start a round aiming for `target` (0 to 1) of moles hit, or turn adaptation off with `None`.
+    pub fn reset(&mut self, target: Option<f32>)
+    {
+        *self = Self { target, ..default() };
+    }
+
+    pub fn target(&self) -> Option<f32>
+    {
+        return self.target;
+    }
+
+    /// A mole was hit after being up for `reaction_sec`.
+    pub fn record_hit(&mut self, reaction_sec: f32)
+    {
+        self.reaction_times.push_back(reaction_sec);
+        if self.reaction_times.len() > ADAPTIVE_WINDOW
+        {
+            self.reaction_times.pop_front();
+        }
+
+        self.record(true);
+    }
+
+    /// A mole got away or the wrong key was pressed.
+    pub fn record_miss(&mut self)
+    {
+        self.record(false);
+    }
+
+    fn record(&mut self, hit: bool)
+    {
+        let Some(target) = self.target else
+        {
+            return;
+        };
+
+        self.outcomes.push_back(hit);
+        if self.outcomes.len() > ADAPTIVE_WINDOW
+        {
+            self.outcomes.pop_front();
+        }
+
+        if self.outcomes.len() >= ADAPTIVE_MIN_SAMPLES
+        {
+            let hit_rate = self.outcomes.iter().filter(|hit| **hit).count() as f32 / self.outcomes.len() as f32;
+            self.level = (self.level + (hit_rate - target) * ADAPTIVE_RATE).clamp(0.0, 1.0);
+        }
+    }
+
+    fn average_reaction(&self) -> f32
+    {
+        if self.reaction_times.is_empty()
+        {
+            return 0.0;
+        }
+
+        return self.reaction_times.iter().sum::<f32>() / self.reaction_times.len() as f32;
+    }
+}
+
+impl DifficultyCurve for AdaptiveDifficulty
+{
+    fn name(&self) -> &str
+    {
+        return "Adaptive";
+    }
+
+    fn max_moles_up(&self, _elapsed_sec: f32) -> f32
+    {
+        return 1.0 + self.level * 11.0;
+    }
+
+    fn up_time_scale(&self, _elapsed_sec: f32) -> f32
+    {
+        let reaction_floor = self.average_reaction() * 2.0 / TYPICAL_UP_TIME;
+        return (1.5 - self.level).max(reaction_floor);
+    }
+
+    fn hidden_time_scale(&self, _elapsed_sec: f32) -> f32
+    {
+        return 1.5 - self.level * 1.1;
+    }
+
+    fn bonk_cooldown_scale(&self, _elapsed_sec: f32) -> f32
+    {
+        return 1.0;
+    }
+}
+
+// =============================================
+// SELECTION
+// =============================================
+
+/// Difficulty curves that can be picked on the title screen.
+#[derive(Resource)]
+pub struct DifficultyCurves
+{
+    curves: Vec<Box<dyn DifficultyCurve>>,
+    curr_idx: usize,
+}
+
+impl DifficultyCurves
+{
+    pub fn add(&mut self, curve: Box<dyn DifficultyCurve>)
+    {
+        self.curves.push(curve);
+    }
+
+    pub fn current(&self) -> &dyn DifficultyCurve
+    {
+        return self.curves[self.curr_idx].as_ref();
+    }
+
+    pub fn cycle(&mut self)
+    {
+        self.curr_idx = (self.curr_idx + 1) % self.curves.len();
+    }
+
+    /// Select the curve called `name`, if there is one.
+    pub fn select(&mut self, name: &str) -> bool
+    {
+        if let Some(idx) = self.curves.iter().position(|curve| curve.name() == name)
+        {
+            self.curr_idx = idx;
+            return true;
+        }
+
+        return false;
+    }
+}
+
+impl Default for DifficultyCurves
+{
+    fn default() -> Self
+    {
+        Self
+        {
+            curves: vec![Box::new(PresetCurve::EASY),
+                         Box::new(PresetCurve::NORMAL),
+                         Box::new(PresetCurve::HARD),
+                         Box::new(PresetCurve::INSANE)],
+            curr_idx: 1,
+        }
+    }
+}
+
+// =============================================
+// LOADER
+// =============================================
+#[derive(Default)]
+pub struct CurveLoader;
+
+#[derive(Debug)]
+pub enum CurveLoaderError
+{
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    /// A point (by index) scales a time by zero or less.
+    NonPositiveScale(usize),
+    /// A point (by index) comes before the one ahead of it.
+    Unsorted(usize),
+}
+
+impl fmt::Display for CurveLoaderError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            CurveLoaderError::Io(err) => write!(f, "Could not read difficulty curve: {}", err),
+            CurveLoaderError::Ron(err) => write!(f, "Could not parse difficulty curve: {}", err),
+            CurveLoaderError::NonPositiveScale(idx) => write!(f, "Point {} of difficulty curve has a time scale that is not above 0", idx),
+            CurveLoaderError::Unsorted(idx) => write!(f, "Point {} of difficulty curve is earlier than the point before it", idx),
+        }
+    }
+}
+
+impl std::error::Error for CurveLoaderError {}
+
+impl AssetLoader for CurveLoader
+{
+    type Asset = PiecewiseCurve;
+    type Settings = ();
+    type Error = CurveLoaderError;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), _load_context: &mut LoadContext<'_>)
+        -> Result<Self::Asset, Self::Error>
+    {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(CurveLoaderError::Io)?;
+
+        let curve = ron::de::from_bytes::<PiecewiseCurve>(&bytes).map_err(CurveLoaderError::Ron)?;
+        curve.validate()?;
+
+        return Ok(curve);
+    }
+
+    fn extensions(&self) -> &[&str]
+    {
+        &["curve.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn parse(points: &str) -> PiecewiseCurve
+    {
+        return ron::from_str(&format!("(name: \"Test\", points: [{}])", points)).unwrap();
+    }
+
+    fn ramp() -> PiecewiseCurve
+    {
+        return parse("(time: 10.0, max_moles: 2.0, up_time: 1.0, bonk_cooldown: 2.0), (time: 30.0, max_moles: 6.0, up_time: 0.5, bonk_cooldown: 1.0)");
+    }
+
+    #[test]
+    fn values_are_interpolated_between_points()
+    {
+        let curve = ramp();
+        assert_eq!(curve.max_moles_up(20.0), 4.0);
+        assert_eq!(curve.up_time_scale(20.0), 0.75);
+        assert_eq!(curve.bonk_cooldown_scale(25.0), 1.25);
+    }
+
+    #[test]
+    fn values_are_held_past_the_ends()
+    {
+        let curve = ramp();
+        assert_eq!(curve.max_moles_up(0.0), 2.0);
+        assert_eq!(curve.max_moles_up(100.0), 6.0);
+        assert_eq!(curve.up_time_scale(100.0), 0.5);
+    }
+
+    #[test]
+    fn scales_default_to_one()
+    {
+        let curve = parse("(time: 0.0, max_moles: 3.0)");
+        assert_eq!(curve.hidden_time_scale(5.0), 1.0);
+        assert_eq!(curve.bonk_cooldown_scale(5.0), 1.0);
+    }
+
+    #[test]
+    fn empty_curves_do_not_scale()
+    {
+        assert_eq!(parse("").up_time_scale(5.0), 1.0);
+    }
+
+    #[test]
+    fn non_positive_scales_are_rejected()
+    {
+        let curve = parse("(time: 0.0, max_moles: 3.0), (time: 10.0, max_moles: 3.0, hidden_time: 0.0)");
+        assert!(matches!(curve.validate(), Err(CurveLoaderError::NonPositiveScale(1))));
+    }
+
+    #[test]
+    fn unsorted_points_are_rejected()
+    {
+        let curve = parse("(time: 10.0, max_moles: 3.0), (time: 5.0, max_moles: 3.0)");
+        assert!(matches!(curve.validate(), Err(CurveLoaderError::Unsorted(1))));
+        assert!(ramp().validate().is_ok());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::keyboard::slot_of_key;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Length of a Blitz round in seconds.
+pub const BLITZ_DURATION: f32 = 60.0;
+
+/// Rules a round is played by. Each mode keeps its own high score tables.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GameMode
+{
+    /// Whack moles until you run out of hearts.
+    #[default]
+    Classic,
+    /// Score as much as possible before the clock runs out. Mistakes cost points instead of hearts.
+    Blitz,
+    /// Practice without hearts or a clock, optionally on a subset of keys, with a per-key report at the end.
+    Zen,
+    /// Moles rise one at a time to spell out words, which must be hit in order.
+    Word,
+    /// A run of levels, each ending in a boss wave once its target score is reached.
+    Levels,
+    /// Two players share the keyboard, one on each half, until one of them runs out of hearts.
+    Versus,
+    /// Blitz rules, raced online against other players starting from the same seed.
+    Race,
+}
+
+impl GameMode
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            GameMode::Classic => "Classic",
+            GameMode::Blitz => "Blitz",
+            GameMode::Zen => "Zen",
+            GameMode::Word => "Word",
+            GameMode::Levels => "Levels",
+            GameMode::Versus => "Versus",
+            GameMode::Race => "Race",
+        }
+    }
+
+    pub fn cycle(&mut self)
+    {
+        *self = match self
+        {
+            GameMode::Classic => GameMode::Blitz,
+            GameMode::Blitz => GameMode::Zen,
+            GameMode::Zen => GameMode::Word,
+            GameMode::Word => GameMode::Levels,
+            GameMode::Levels => GameMode::Versus,
+            GameMode::Versus => GameMode::Race,
+            GameMode::Race => GameMode::Classic,
+        };
+    }
+
+    /// Seconds a round lasts, for modes played against the clock.
+    pub fn time_limit(&self) -> Option<f32>
+    {
+        match self
+        {
+            GameMode::Classic | GameMode::Zen | GameMode::Word | GameMode::Levels | GameMode::Versus => None,
+            GameMode::Blitz | GameMode::Race => Some(BLITZ_DURATION),
+        }
+    }
+
+    /// Whether mistakes cost hearts, ending the round when they run out.
+    pub fn uses_hearts(&self) -> bool
+    {
+        return matches!(self, GameMode::Classic | GameMode::Word | GameMode::Levels | GameMode::Versus);
+    }
+
+    /// Whether golden, bomb, armored and decoy moles turn up. Practice and word
+    /// modes stick to plain moles so the player can focus on the keys, and each level picks for itself.
+    pub fn has_special_moles(&self) -> bool
+    {
+        return matches!(self, GameMode::Classic | GameMode::Blitz | GameMode::Race);
+    }
+
+    /// Practice doesn't count towards the high scores, and neither does a head-to-head or a race.
+    pub fn has_leaderboard(&self) -> bool
+    {
+        return !matches!(self, GameMode::Zen | GameMode::Versus | GameMode::Race);
+    }
+}
+
+/// Half of the keyboard a player whacks with in Versus mode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Side
+{
+    Left,
+    Right,
+}
+
+impl Side
+{
+    /// The side `key_code` is on. Keys neither hand owns, like the space bar, are on no side.
+    pub fn of_key(key_code: KeyCode) -> Option<Side>
+    {
+        if KeyDrill::LeftHand.includes(key_code)
+        {
+            return Some(Side::Left);
+        }
+        if KeyDrill::RightHand.includes(key_code)
+        {
+            return Some(Side::Right);
+        }
+        return None;
+    }
+
+    pub fn idx(&self) -> usize
+    {
+        match self
+        {
+            Side::Left => 0,
+            Side::Right => 1,
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Side::Left => "Left",
+            Side::Right => "Right",
+        }
+    }
+}
+
+/// Keys moles may come up on in Zen mode.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum KeyDrill
+{
+    #[default]
+    AllKeys,
+    LeftHand,
+    RightHand,
+    NumberRow,
+    TopRow,
+    HomeRow,
+    BottomRow,
+}
+
+impl KeyDrill
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            KeyDrill::AllKeys => "All keys",
+            KeyDrill::LeftHand => "Left hand",
+            KeyDrill::RightHand => "Right hand",
+            KeyDrill::NumberRow => "Number row",
+            KeyDrill::TopRow => "Top row",
+            KeyDrill::HomeRow => "Home row",
+            KeyDrill::BottomRow => "Bottom row",
+        }
+    }
+
+    pub fn cycle(&mut self)
+    {
+        *self = match self
+        {
+            KeyDrill::AllKeys => KeyDrill::LeftHand,
+            KeyDrill::LeftHand => KeyDrill::RightHand,
+            KeyDrill::RightHand => KeyDrill::NumberRow,
+            KeyDrill::NumberRow => KeyDrill::TopRow,
+            KeyDrill::TopRow => KeyDrill::HomeRow,
+            KeyDrill::HomeRow => KeyDrill::BottomRow,
+            KeyDrill::BottomRow => KeyDrill::AllKeys,
+        };
+    }
+
+    /// Is `key_code` part of this drill? Keys off the main block (like the numpad) are only in `AllKeys`.
+    pub fn includes(&self, key_code: KeyCode) -> bool
+    {
+        if *self == KeyDrill::AllKeys
+        {
+            return true;
+        }
+
+        let Some((row, col)) = slot_of_key(key_code) else
+        {
+            return false;
+        };
+
+        match self
+        {
+            KeyDrill::AllKeys => true,
+            // The space bar is left to the thumbs, so it isn't in either hand.
+            KeyDrill::LeftHand => row < 4 && col <= 4,
+            KeyDrill::RightHand => row < 4 && col > 4,
+            KeyDrill::NumberRow => row == 0,
+            KeyDrill::TopRow => row == 1,
+            KeyDrill::HomeRow => row == 2,
+            KeyDrill::BottomRow => row == 3,
+        }
+    }
+}
@@ -1,9 +1,31 @@
-use crate::loading::{AudioAssets, FontAssets, TextureAssets};
+use crate::audio::MusicChannel;
+use crate::game_mode::{GameMode, KeyDrill, Side};
+use crate::difficulty::{AdaptiveDifficulty, DifficultyCurve, DifficultyCurves, PiecewiseCurve};
+use crate::board::{BoardDefinition, HoleTiming, ResolvedHole, SelectedBoard};
+use crate::input::{InputBinding, InputMode, KeyPress, KeyPresses};
+use crate::leaderboard::{Leaderboard, ScoreEntry, MAX_NAME_LEN};
+use crate::levels::{BossWave, LevelProgress, LEVELS};
+use crate::replay::{Replay, ReplayPlayer, ReplayRecorder};
+use crate::keyboard::{mirrored_key, to_upper_glyph, KeyboardLayout, KeyboardLayouts};
+use crate::online::{ClientMessage, RaceClient};
+use crate::loading::{AudioAssets, BoardAssets, DifficultyAssets, FontAssets, TextureAssets};
+use crate::rng::GameRng;
+use crate::scoring::Scoring;
+use crate::words::{pick_word, spellable_words, WordProgress};
+use crate::settings::{Settings, SettingsMenu, MAX_HEARTS};
 use crate::GameState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::ecs::system::SystemParam;
+use bevy::input::{ButtonState, InputSystem};
+use bevy::picking::events::{Down, Pointer};
+use bevy::picking::pointer::PointerButton;
+use bevy::picking::{PickSet, PickingBehavior};
 use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl};
+use bevy::window::{PrimaryWindow, SystemCursorIcon, WindowFocused, WindowOccluded};
+use bevy::winit::cursor::{CursorIcon, CustomCursor};
+use bevy_kira_audio::{Audio, AudioChannel, AudioControl};
 use std::cmp;
-use std::{collections::HashMap, time::Duration};
+use std::{collections::{HashMap, HashSet}, time::Duration};
 use bevy::text::FontSmoothing;
 use bevy::time::Stopwatch;
 use rand::Rng;
@@ -11,14 +33,20 @@ use rand::Rng;
 // =============================================
 // GAMESTATE
 // =============================================
-const MAX_MISTAKES : i32 = 10;
-
 #[derive(Eq, PartialEq, Clone, Copy, Debug)]
 enum RoundState
 {
     Begin,
+    Settings,
     Round,
-    GameOver
+    Paused,
+    GameOver,
+    /// Picking which unlocked level to play, in Levels mode.
+    LevelSelect,
+    /// A level's boss wave was beaten.
+    LevelComplete,
+    /// Waiting on the race relay for a race to start, in Race mode.
+    Lobby,
 }
 
 #[derive(Resource)]
@@ -26,8 +54,37 @@ struct GameManager
 {
     time_since_round_start : Stopwatch,
     curr_state: RoundState,
+    mode: GameMode,
     moles_hit: i32,
+    /// Moles that got away plus wrong keys pressed.
     moles_missed: i32,
+    wrong_keys: i32,
+    scoring: Scoring,
+    power_ups: ActivePowerUps,
+    max_health: i32,
+    /// Hits and misses per mole glyph, for the Zen mode report.
+    key_stats: HashMap<char, KeyStats>,
+    /// Word being spelled in Word mode.
+    word: Option<WordProgress>,
+    /// Words that can be spelled on the board, worked out when a Word round starts.
+    words: Vec<String>,
+    word_bonus: i32,
+    /// Index into `LEVELS` of the level being played, in Levels mode.
+    level: Option<usize>,
+    /// Level picked on the level select screen, started once its board is in place.
+    queued_level: Option<usize>,
+    /// Seed of a race the relay just started, started once its board is in place.
+    race_seed: Option<u64>,
+    /// Level highlighted on the level select screen.
+    level_cursor: usize,
+    /// Boss wave running at the end of the level, once its target score is reached.
+    boss: Option<BossWave>,
+    /// Left and right player in Versus mode, who use these instead of the fields above.
+    versus: [VersusPlayer; 2],
+    /// Digits typed on the title screen to start the next round from a fixed seed.
+    seed_entry: String,
+    /// Name being typed on the game over screen, when the score made the leaderboard.
+    name_entry: Option<String>,
     // music_handle: Handle<AudioSource>,
     // bonk_handle: Handle<AudioSource>,
     // nope_handle: Handle<AudioSource>,
@@ -42,8 +99,25 @@ impl GameManager
         {
             time_since_round_start: Stopwatch::new(),
             curr_state: RoundState::Begin,
+            mode: GameMode::Classic,
             moles_hit: 0,
             moles_missed: 0,
+            wrong_keys: 0,
+            scoring: Scoring::default(),
+            power_ups: ActivePowerUps::default(),
+            max_health: MAX_HEARTS,
+            key_stats: HashMap::new(),
+            word: None,
+            words: Vec::new(),
+            word_bonus: 0,
+            level: None,
+            queued_level: None,
+            race_seed: None,
+            level_cursor: 0,
+            boss: None,
+            versus: [VersusPlayer::default(); 2],
+            seed_entry: String::new(),
+            name_entry: None,
             // music_handle: Handle::default(),
             // bonk_handle: Handle::default(),
             // nope_handle: Handle::default(),
@@ -51,12 +125,23 @@ impl GameManager
         }
     }
 
-    fn start_round(&mut self)
+    fn start_round(&mut self, mode: GameMode, max_health: i32)
     {
         self.curr_state = RoundState::Round;
         self.time_since_round_start.reset();
+        self.mode = mode;
         self.moles_hit = 0;
         self.moles_missed = 0;
+        self.wrong_keys = 0;
+        self.scoring = Scoring::default();
+        self.power_ups = ActivePowerUps::default();
+        // Each player gets half of the healthbar.
+        self.max_health = if mode == GameMode::Versus { max_health.min(MAX_HEARTS / 2) } else { max_health };
+        self.key_stats.clear();
+        self.word = None;
+        self.word_bonus = 0;
+        self.boss = None;
+        self.versus = [VersusPlayer::default(); 2];
     }
 
     fn game_over(&mut self)
@@ -66,65 +151,1160 @@ impl GameManager
 
     fn get_curr_health(&self) -> i32
     {
-        return cmp::max(0, MAX_MISTAKES - self.moles_missed);
+        return cmp::max(0, self.max_health - self.moles_missed);
+    }
+
+    fn score(&self) -> i32
+    {
+        match self.mode
+        {
+            GameMode::Classic | GameMode::Zen | GameMode::Levels | GameMode::Versus => self.scoring.total(),
+            GameMode::Blitz | GameMode::Race => self.scoring.total() - self.moles_missed,
+            GameMode::Word => self.scoring.total() + self.word_bonus,
+        }
+    }
+
+    /// Seconds left in a round played against the clock.
+    fn time_left(&self) -> Option<f32>
+    {
+        return self.mode.time_limit().map(|limit| (limit - self.time_since_round_start.elapsed_secs()).max(0.0));
+    }
+
+    /// The score line of the HUD, with both players' scores in Versus mode.
+    fn score_str(&self) -> String
+    {
+        if self.mode == GameMode::Versus
+        {
+            return format!("Left {}  -  Right {}", self.versus[0].scoring.total(), self.versus[1].scoring.total());
+        }
+
+        return format!("Score: {}", self.score());
+    }
+
+    /// Count a hit on a mole that was up for `reaction_sec`, for the player on `side` in Versus mode.
+    /// Returns the points it was worth before the multiplier, and the multiplier they were scored at.
+    fn add_hit(&mut self, reaction_sec: f32, bonus: i32, side: Option<Side>) -> (i32, i32)
+    {
+        let scoring = match side
+        {
+            Some(side) =>
+            {
+                let player = &mut self.versus[side.idx()];
+                player.moles_hit += 1;
+                &mut player.scoring
+            }
+            None =>
+            {
+                self.moles_hit += 1;
+                &mut self.scoring
+            }
+        };
+
+        return scoring.hit(reaction_sec, bonus);
+    }
+
+    /// Count a mistake, against the player on `side` in Versus mode, unless a shield absorbs it.
+    fn add_mistake(&mut self, wrong_key: bool, side: Option<Side>)
+    {
+        if let Some(side) = side
+        {
+            let player = &mut self.versus[side.idx()];
+            player.moles_missed += 1;
+            player.scoring.miss();
+            return;
+        }
+
+        if self.power_ups.shield
+        {
+            self.power_ups.shield = false;
+            return;
+        }
+
+        self.moles_missed += 1;
+        if wrong_key
+        {
+            self.wrong_keys += 1;
+        }
+        self.scoring.miss();
+    }
+
+    fn activate_power_up(&mut self, power_up: PowerUp)
+    {
+        match power_up
+        {
+            PowerUp::SlowTime => self.power_ups.slow_time_left = SLOW_TIME_DURATION,
+            PowerUp::Shield => self.power_ups.shield = true,
+            // Hearts are lost by missing, so giving one back is un-missing.
+            PowerUp::ExtraHeart => self.moles_missed = cmp::max(0, self.moles_missed - 1),
+        }
+    }
+
+    /// The current word's mole was hit, move on to its next letter.
+    fn advance_word(&mut self)
+    {
+        if let Some(word) = &mut self.word
+        {
+            word.advance();
+            if word.is_finished()
+            {
+                self.word_bonus += word.bonus();
+            }
+        }
+    }
+
+    /// Whether golden, bomb, armored and decoy moles and power-ups turn up this round.
+    fn has_special_moles(&self) -> bool
+    {
+        match self.level
+        {
+            Some(level) => LEVELS[level].special_moles,
+            None => self.mode.has_special_moles(),
+        }
+    }
+
+    fn record_key(&mut self, glyph: char, hit: bool)
+    {
+        let stats = self.key_stats.entry(glyph).or_default();
+        if hit { stats.hits += 1; } else { stats.misses += 1; }
+    }
+
+    /// The `count` keys with the lowest accuracy, worst first.
+    fn weakest_keys(&self, count: usize) -> Vec<(char, KeyStats)>
+    {
+        let mut keys: Vec<(char, KeyStats)> = self.key_stats.iter().map(|(glyph, stats)| (*glyph, *stats)).collect();
+        keys.sort_by(|(a_glyph, a), (b_glyph, b)| a.accuracy().total_cmp(&b.accuracy()).then(a_glyph.cmp(b_glyph)));
+        keys.truncate(count);
+        return keys;
+    }
+
+    /// The player left standing in Versus mode, or the higher scorer if both fell on the same step.
+    fn winner(&self) -> Option<Side>
+    {
+        let [left, right] = &self.versus;
+        let (left_health, right_health) = (left.health(self.max_health), right.health(self.max_health));
+        if left_health != right_health
+        {
+            return Some(if left_health > right_health { Side::Left } else { Side::Right });
+        }
+
+        match left.scoring.total().cmp(&right.scoring.total())
+        {
+            cmp::Ordering::Greater => Some(Side::Left),
+            cmp::Ordering::Less => Some(Side::Right),
+            cmp::Ordering::Equal => None,
+        }
+    }
+
+    fn is_round_over(&self) -> bool
+    {
+        if self.mode == GameMode::Versus
+        {
+            return self.versus.iter().any(|player| player.health(self.max_health) == 0);
+        }
+
+        if self.mode.uses_hearts() && self.get_curr_health() == 0
+        {
+            return true;
+        }
+
+        return self.time_left() == Some(0.0);
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+struct KeyStats
+{
+    hits: u32,
+    misses: u32,
+}
+
+/// Score and hearts of one player in Versus mode.
+#[derive(Clone, Copy, Default, Debug)]
+struct VersusPlayer
+{
+    moles_hit: i32,
+    moles_missed: i32,
+    scoring: Scoring,
+}
+
+impl VersusPlayer
+{
+    fn health(&self, max_health: i32) -> i32
+    {
+        return cmp::max(0, max_health - self.moles_missed);
+    }
+}
+
+impl KeyStats
+{
+    fn accuracy(&self) -> f32
+    {
+        return self.hits as f32 / cmp::max(1, self.hits + self.misses) as f32;
+    }
+}
+
+// =============================================
+// POWER-UPS
+// =============================================
+const SLOW_TIME_DURATION: f32 = 6.0;
+/// How fast mole timers run while slow-time is active.
+const SLOW_TIME_SCALE: f32 = 0.5;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum PowerUp
+{
+    /// Moles move at half speed for a few seconds.
+    SlowTime,
+    /// The next mistake doesn't count.
+    Shield,
+    /// Restores a lost heart.
+    ExtraHeart,
+}
+
+#[derive(Default, Debug)]
+struct ActivePowerUps
+{
+    slow_time_left: f32,
+    shield: bool,
+}
+
+impl ActivePowerUps
+{
+    fn tick(&mut self, delta_secs: f32)
+    {
+        self.slow_time_left = (self.slow_time_left - delta_secs).max(0.0);
+    }
+
+    /// Multiplier on how fast mole timers run.
+    fn time_scale(&self) -> f32
+    {
+        return if self.slow_time_left > 0.0 { SLOW_TIME_SCALE } else { 1.0 };
+    }
+
+    fn describe(&self) -> String
+    {
+        let mut active = Vec::new();
+        if self.slow_time_left > 0.0
+        {
+            active.push(format!("SLOW {:.0}", self.slow_time_left.ceil()));
+        }
+        if self.shield
+        {
+            active.push(String::from("SHIELD"));
+        }
+        return active.join("\n");
+    }
+}
+
+/// Board picked for Versus mode when it is selected.
+const VERSUS_BOARD: &str = "Versus";
+
+/// Options picked on the title screen for the next round.
+#[derive(SystemParam)]
+struct RoundOptions<'w>
+{
+    mode: ResMut<'w, GameMode>,
+    drill: ResMut<'w, KeyDrill>,
+    layouts: ResMut<'w, KeyboardLayouts>,
+    input_mode: ResMut<'w, InputMode>,
+    selected_board: ResMut<'w, SelectedBoard>,
+    curves: ResMut<'w, DifficultyCurves>,
+}
+
+impl Default for GameManager
+{
+    fn default() -> Self
+    {
+        return GameManager::new();
+    }
+}
+
+fn update_gamemanager(keys: Res<ButtonInput<KeyCode>>, 
+                        mut manager: ResMut<GameManager>,
+                        mut options: RoundOptions,
+                        board_assets: Res<BoardAssets>,
+                        boards: Res<Assets<BoardDefinition>>,
+                        mut rng: ResMut<GameRng>,
+                        mut recorder: ResMut<ReplayRecorder>,
+                        mut player: ResMut<ReplayPlayer>,
+                        mut leaderboard: ResMut<Leaderboard>,
+                        mut key_events: EventReader<KeyboardInput>,
+                        settings: Res<Settings>,
+                        mut adaptive: ResMut<AdaptiveDifficulty>,
+                        mut moles: Query<(&mut SpriteAnimator, &mut Mole)>)
+{
+    match manager.curr_state 
+    {
+        RoundState::Begin =>
+        {
+            // A replay picked last frame starts once the moles for its board have been rebuilt.
+            if let Some((seed, adaptive_target, level, starting_hearts)) = player.playing.as_ref()
+                                                    .map(|replay| (replay.seed, replay.adaptive_target, replay.level, replay.starting_hearts))
+            {
+                adaptive.reset(adaptive_target);
+                manager.level = level;
+                start_round(&mut manager, &mut rng, seed, *options.mode, starting_hearts, &mut moles);
+            }
+            else if keys.just_pressed(KeyCode::Tab)
+            {
+                options.layouts.cycle();
+            }
+            else if keys.just_pressed(KeyCode::F1)
+            {
+                options.input_mode.cycle();
+            }
+            // The board stays on the one F6 picked for Versus, since other boards leave moles without a partner to rise with.
+            else if keys.just_pressed(KeyCode::F2) && *options.mode != GameMode::Versus
+            {
+                options.selected_board.cycle(board_assets.boards.len());
+            }
+            else if keys.just_pressed(KeyCode::F3)
+            {
+                if let Some(replay) = Replay::load_last()
+                {
+                    options.layouts.select(&replay.layout);
+                    if !options.curves.select(&replay.difficulty)
+                    {
+                        warn!("Replay uses unknown difficulty {}", replay.difficulty);
+                    }
+                    if let Some(idx) = board_index(&board_assets, &boards, &replay.board)
+                    {
+                        options.selected_board.idx = idx;
+                    }
+                    *options.input_mode = replay.input_mode;
+                    *options.mode = replay.mode;
+                    *options.drill = replay.drill;
+                    player.play(replay);
+                }
+            }
+            else if keys.just_pressed(KeyCode::F4)
+            {
+                manager.curr_state = RoundState::Settings;
+            }
+            else if keys.just_pressed(KeyCode::F5)
+            {
+                options.curves.cycle();
+            }
+            else if keys.just_pressed(KeyCode::F6)
+            {
+                options.mode.cycle();
+                // Versus needs every key mirrored on the other half, which only its own board is sure to have.
+                if let Some(idx) = board_index(&board_assets, &boards, VERSUS_BOARD).filter(|_| *options.mode == GameMode::Versus)
+                {
+                    options.selected_board.idx = idx;
+                }
+            }
+            else if keys.just_pressed(KeyCode::F7) && *options.mode == GameMode::Zen
+            {
+                options.drill.cycle();
+            }
+            else if keys.just_pressed(KeyCode::Backspace)
+            {
+                manager.seed_entry.pop();
+            }
+            else if *options.mode == GameMode::Levels && manager.queued_level.is_none()
+                        && (keys.just_pressed(KeyCode::Enter) || keys.just_pressed(KeyCode::Space))
+            {
+                manager.curr_state = RoundState::LevelSelect;
+            }
+            else if *options.mode == GameMode::Race && manager.race_seed.is_none()
+                        && (keys.just_pressed(KeyCode::Enter) || keys.just_pressed(KeyCode::Space))
+            {
+                manager.curr_state = RoundState::Lobby;
+            }
+            else if manager.queued_level.is_some() || manager.race_seed.is_some()
+                        || keys.just_pressed(KeyCode::Enter) || keys.just_pressed(KeyCode::Space)
+            {
+                manager.level = manager.queued_level.take();
+                let seed = manager.race_seed.take()
+                                .or_else(|| manager.seed_entry.parse::<u64>().ok())
+                                .unwrap_or_else(|| rand::thread_rng().gen());
+                // Adaptive difficulty would give each racer different moles, and would take over from each level's own curve.
+                let adaptive_target = if matches!(*options.mode, GameMode::Race | GameMode::Levels) { None } else { settings.adaptive_target };
+                let board_name = current_board(&options.selected_board, &board_assets, &boards).map_or(String::new(), |board| board.name.clone());
+
+                recorder.recording = Some(Replay
+                {
+                    seed,
+                    mode: *options.mode,
+                    drill: *options.drill,
+                    board: board_name,
+                    layout: String::from(options.layouts.current().name()),
+                    input_mode: *options.input_mode,
+                    difficulty: options.curves.current().name().to_string(),
+                    adaptive_target,
+                    level: manager.level,
+                    starting_hearts: settings.starting_hearts,
+                    presses: Vec::new(),
+                });
+                adaptive.reset(adaptive_target);
+                start_round(&mut manager, &mut rng, seed, *options.mode, settings.starting_hearts, &mut moles);
+            }
+            else
+            {
+                edit_with_arrows(&keys, &mut manager.seed_entry, SEED_GLYPHS, MAX_SEED_LEN);
+                for key in keys.get_just_pressed()
+                {
+                    if let Some(digit) = key_code_to_digit(*key)
+                    {
+                        if manager.seed_entry.len() < MAX_SEED_LEN
+                        {
+                            manager.seed_entry.push(digit);
+                        }
+                    }
+                }
+            }
+        }
+        RoundState::Settings | RoundState::LevelSelect | RoundState::LevelComplete | RoundState::Lobby => {}
+        RoundState::Round | RoundState::Paused =>
+        {
+            // Don't let keys whacked during the round end up in the name entry.
+            key_events.clear();
+        }
+        RoundState::GameOver =>
+        {
+            // The recording is only still open on the first frame after a live round ends.
+            if recorder.recording.is_some()
+            {
+                // The key that ended the round in the fixed step is still in this frame's events.
+                key_events.clear();
+            }
+            if let Some(replay) = recorder.recording.as_ref().filter(|_| manager.mode.has_leaderboard())
+            {
+                if leaderboard.qualifies(manager.mode.name(), &replay.board, manager.score())
+                {
+                    manager.name_entry = Some(leaderboard.last_name.clone());
+                }
+            }
+            recorder.finish();
+
+            if let Some(name) = &mut manager.name_entry
+            {
+                for event in key_events.read()
+                {
+                    if event.state != ButtonState::Pressed
+                    {
+                        continue;
+                    }
+
+                    match &event.logical_key
+                    {
+                        Key::Backspace => { name.pop(); },
+                        Key::Character(chars) =>
+                        {
+                            for c in chars.chars().filter(|c| c.is_alphanumeric())
+                            {
+                                if name.chars().count() < MAX_NAME_LEN
+                                {
+                                    name.push(to_upper_glyph(c));
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                edit_with_arrows(&keys, name, NAME_GLYPHS, MAX_NAME_LEN);
+            }
+
+            if keys.just_pressed(KeyCode::Enter)
+            {
+                if let Some(name) = manager.name_entry.take()
+                {
+                    if !name.is_empty()
+                    {
+                        let board_name = current_board(&options.selected_board, &board_assets, &boards).map_or("", |board| board.name.as_str());
+                        let entry = ScoreEntry { name, score: manager.score(), seed: rng.seed() };
+                        leaderboard.submit(manager.mode.name(), board_name, entry);
+                    }
+                }
+
+                manager.curr_state = if manager.level.is_some()
+                {
+                    RoundState::LevelSelect
+                }
+                else if manager.mode == GameMode::Race && !player.is_playing()
+                {
+                    RoundState::Lobby
+                }
+                else
+                {
+                    RoundState::Begin
+                };
+                player.stop();
+            }
+        }
+    }
+}
+
+fn update_settings_menu(keys: Res<ButtonInput<KeyCode>>,
+                        mut manager: ResMut<GameManager>,
+                        mut settings: ResMut<Settings>,
+                        mut menu: ResMut<SettingsMenu>)
+{
+    if manager.curr_state != RoundState::Settings
+    {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::ArrowUp) || keys.just_pressed(KeyCode::ArrowDown)
+    {
+        menu.move_selection(keys.just_pressed(KeyCode::ArrowDown));
+    }
+    else if keys.just_pressed(KeyCode::ArrowLeft) || keys.just_pressed(KeyCode::ArrowRight)
+    {
+        menu.adjust(&mut settings, keys.just_pressed(KeyCode::ArrowRight));
+    }
+    else if keys.just_pressed(KeyCode::Escape) || keys.just_pressed(KeyCode::Enter)
+    {
+        settings.save();
+        manager.curr_state = RoundState::Begin;
+    }
+}
+
+/// Pause on Escape or when the window loses focus, and handle the pause menu. Races can't be paused,
+/// since the other players' rounds keep going.
+fn update_pause(keys: Res<ButtonInput<KeyCode>>,
+                mut focus_events: EventReader<WindowFocused>,
+                mut occluded_events: EventReader<WindowOccluded>,
+                mut manager: ResMut<GameManager>,
+                mut rng: ResMut<GameRng>,
+                mut recorder: ResMut<ReplayRecorder>,
+                mut player: ResMut<ReplayPlayer>,
+                settings: Res<Settings>,
+                music: Res<AudioChannel<MusicChannel>>,
+                mut adaptive: ResMut<AdaptiveDifficulty>,
+                mut moles: Query<(&mut SpriteAnimator, &mut Mole)>)
+{
+    // Read every frame so focus changes from the title screen don't pause the next round.
+    let lost_focus = focus_events.read().any(|event| !event.focused);
+    let hidden = occluded_events.read().any(|event| event.occluded);
+
+    match manager.curr_state
+    {
+        RoundState::Round =>
+        {
+            if (keys.just_pressed(KeyCode::Escape) || lost_focus || hidden) && manager.mode != GameMode::Race
+            {
+                manager.curr_state = RoundState::Paused;
+                music.pause();
+            }
+        }
+        RoundState::Paused =>
+        {
+            if keys.just_pressed(KeyCode::Escape)
+            {
+                manager.curr_state = RoundState::Round;
+                music.resume();
+            }
+            else if keys.just_pressed(KeyCode::KeyR)
+            {
+                // Same seed, so a restart is a fresh attempt at the same round.
+                if let Some(replay) = &mut recorder.recording
+                {
+                    replay.presses.clear();
+                }
+                if let Some(replay) = player.playing.clone()
+                {
+                    player.play(replay);
+                }
+                let adaptive_target = adaptive.target();
+                adaptive.reset(adaptive_target);
+
+                // Hearts come from the round being restarted, in case the setting changed since.
+                let starting_hearts = player.playing.as_ref().or(recorder.recording.as_ref())
+                                        .map_or(settings.starting_hearts, |replay| replay.starting_hearts);
+                let (seed, mode) = (rng.seed(), manager.mode);
+                start_round(&mut manager, &mut rng, seed, mode, starting_hearts, &mut moles);
+                music.resume();
+            }
+            else if keys.just_pressed(KeyCode::KeyQ)
+            {
+                for (mut mole_sprite, mut mole) in &mut moles
+                {
+                    mole.status = MoleState::Hidden;
+                    mole_sprite.play_anim(MOLE_HIDE_ANIM);
+                }
+
+                if manager.mode == GameMode::Zen
+                {
+                    // Practice only ends when the player says so, so finishing goes to the report.
+                    manager.game_over();
+                }
+                else
+                {
+                    // An abandoned round isn't worth keeping as the last replay.
+                    recorder.recording = None;
+                    player.stop();
+                    manager.curr_state = if manager.level.is_some() { RoundState::LevelSelect } else { RoundState::Begin };
+                }
+                music.resume();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Handle the level select and level complete screens of Levels mode.
+fn update_levels(keys: Res<ButtonInput<KeyCode>>,
+                    mut manager: ResMut<GameManager>,
+                    mut options: RoundOptions,
+                    board_assets: Res<BoardAssets>,
+                    boards: Res<Assets<BoardDefinition>>,
+                    mut progress: ResMut<LevelProgress>,
+                    mut recorder: ResMut<ReplayRecorder>,
+                    mut player: ResMut<ReplayPlayer>)
+{
+    match manager.curr_state
+    {
+        RoundState::LevelSelect =>
+        {
+            let num_unlocked = progress.num_unlocked();
+            if keys.just_pressed(KeyCode::ArrowUp)
+            {
+                manager.level_cursor = (manager.level_cursor + num_unlocked - 1) % num_unlocked;
+            }
+            else if keys.just_pressed(KeyCode::ArrowDown)
+            {
+                manager.level_cursor = (manager.level_cursor + 1) % num_unlocked;
+            }
+            else if keys.just_pressed(KeyCode::Enter) || keys.just_pressed(KeyCode::Space)
+            {
+                let level = manager.level_cursor.min(num_unlocked - 1);
+                queue_level(&mut manager, &mut options, &board_assets, &boards, level);
+            }
+            else if keys.just_pressed(KeyCode::Escape)
+            {
+                manager.curr_state = RoundState::Begin;
+            }
+        }
+        RoundState::LevelComplete =>
+        {
+            // The recording is only still open on the first frame after the level is beaten, and a replay unlocks nothing.
+            if recorder.recording.is_some()
+            {
+                recorder.finish();
+                if let Some(level) = manager.level
+                {
+                    progress.complete(level);
+                }
+            }
+
+            let next_level = manager.level.map(|level| level + 1).filter(|level| *level < LEVELS.len());
+            if keys.just_pressed(KeyCode::Enter)
+            {
+                player.stop();
+                match next_level
+                {
+                    Some(level) if progress.is_unlocked(level) => queue_level(&mut manager, &mut options, &board_assets, &boards, level),
+                    _ => manager.curr_state = RoundState::LevelSelect,
+                }
+            }
+            else if keys.just_pressed(KeyCode::Escape)
+            {
+                player.stop();
+                manager.curr_state = RoundState::LevelSelect;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Talk to the race relay: join its lobby, start races and keep everyone up to date with our score.
+fn update_race(keys: Res<ButtonInput<KeyCode>>,
+                time: Res<Time>,
+                mut manager: ResMut<GameManager>,
+                mut options: RoundOptions,
+                board_assets: Res<BoardAssets>,
+                boards: Res<Assets<BoardDefinition>>,
+                settings: Res<Settings>,
+                leaderboard: Res<Leaderboard>,
+                mut client: ResMut<RaceClient>)
+{
+    let in_race = manager.mode == GameMode::Race
+                    && matches!(manager.curr_state, RoundState::Round | RoundState::Paused | RoundState::GameOver);
+    if manager.curr_state != RoundState::Lobby && !in_race
+    {
+        // Leaving the race screens, by quitting or watching a replay, leaves the lobby too.
+        if client.is_connected() && manager.race_seed.is_none()
+        {
+            client.disconnect();
+        }
+        return;
+    }
+
+    let name = if leaderboard.last_name.is_empty() { String::from("PLAYER") } else { leaderboard.last_name.clone() };
+    if manager.curr_state == RoundState::Lobby
+    {
+        if keys.just_pressed(KeyCode::Escape)
+        {
+            client.disconnect();
+            manager.curr_state = RoundState::Begin;
+            return;
+        }
+
+        if !client.is_connected() && client.error.is_none()
+        {
+            client.connect(&settings.relay_address, &name);
+        }
+        else if keys.just_pressed(KeyCode::Enter) || keys.just_pressed(KeyCode::Space)
+        {
+            let board = current_board(&options.selected_board, &board_assets, &boards).map_or(String::new(), |board| board.name.clone());
+            client.send(&ClientMessage::Start { board, difficulty: options.curves.current().name().to_string() });
+        }
+        client.keep_alive(&name, time.delta_secs());
+    }
+    else
+    {
+        client.report_score(manager.score(), manager.curr_state == RoundState::GameOver, time.delta_secs());
+    }
+
+    // A race started while we're still mid-round goes on without us.
+    if let Some((seed, board, difficulty)) = client.receive().filter(|_| matches!(manager.curr_state, RoundState::Lobby | RoundState::GameOver))
+    {
+        match board_index(&board_assets, &boards, &board)
+        {
+            Some(idx) => options.selected_board.idx = idx,
+            None => warn!("Race uses unknown board {}", board),
+        }
+        if !options.curves.select(&difficulty)
+        {
+            warn!("Race uses unknown difficulty {}", difficulty);
+        }
+
+        manager.name_entry = None;
+        manager.race_seed = Some(seed);
+        manager.curr_state = RoundState::Begin;
+    }
+}
+
+/// Switch to the board and difficulty of `level`, and have the title screen start it once the moles are rebuilt.
+fn queue_level(manager: &mut GameManager,
+                options: &mut RoundOptions,
+                board_assets: &BoardAssets,
+                boards: &Assets<BoardDefinition>,
+                level: usize)
+{
+    let definition = &LEVELS[level];
+    match board_index(board_assets, boards, definition.board)
+    {
+        Some(idx) => options.selected_board.idx = idx,
+        None => warn!("Level {} uses unknown board {}", definition.name, definition.board),
+    }
+    if !options.curves.select(definition.difficulty)
+    {
+        warn!("Level {} uses unknown difficulty {}", definition.name, definition.difficulty);
+    }
+
+    manager.level_cursor = level;
+    manager.queued_level = Some(level);
+    manager.curr_state = RoundState::Begin;
+}
+
+/// The menu key a gamepad button stands in for on the current screen, so every menu can be used with a gamepad.
+fn menu_key_for_button(state: RoundState, button: GamepadButton) -> Option<KeyCode>
+{
+    let key_code = match (state, button)
+    {
+        // During a round the buttons whack moles, apart from Start which pauses.
+        (RoundState::Round, GamepadButton::Start) => KeyCode::Escape,
+        (RoundState::Round, _) => return None,
+        (RoundState::Paused, GamepadButton::Start | GamepadButton::East) => KeyCode::Escape,
+        (RoundState::Paused, GamepadButton::North) => KeyCode::KeyR,
+        (RoundState::Paused, GamepadButton::West) => KeyCode::KeyQ,
+        (RoundState::Begin, GamepadButton::LeftTrigger2) => KeyCode::Tab,
+        (RoundState::Begin, GamepadButton::LeftThumb) => KeyCode::F1,
+        (RoundState::Begin, GamepadButton::West) => KeyCode::F6,
+        (RoundState::Begin, GamepadButton::North) => KeyCode::F2,
+        (RoundState::Begin, GamepadButton::LeftTrigger) => KeyCode::F5,
+        (RoundState::Begin, GamepadButton::RightTrigger) => KeyCode::F7,
+        (RoundState::Begin, GamepadButton::RightTrigger2) => KeyCode::F3,
+        (RoundState::Begin, GamepadButton::Select) => KeyCode::F4,
+        (_, GamepadButton::DPadUp) => KeyCode::ArrowUp,
+        (_, GamepadButton::DPadDown) => KeyCode::ArrowDown,
+        (_, GamepadButton::DPadLeft) => KeyCode::ArrowLeft,
+        (_, GamepadButton::DPadRight) => KeyCode::ArrowRight,
+        (_, GamepadButton::South | GamepadButton::Start) => KeyCode::Enter,
+        (_, GamepadButton::East) => KeyCode::Escape,
+        _ => return None,
+    };
+
+    return Some(key_code);
+}
+
+/// In Mouse/Touch mode a tap outside a round confirms, so a phone can get through the menus,
+/// and a tap on the pause screen resumes.
+fn menu_key_for_tap(state: RoundState) -> Option<KeyCode>
+{
+    match state
+    {
+        RoundState::Round => None,
+        RoundState::Paused => Some(KeyCode::Escape),
+        _ => Some(KeyCode::Enter),
+    }
+}
+
+/// Press the menu keys that gamepad buttons and taps stand in for, and release them again the next frame.
+fn press_menu_keys(manager: Res<GameManager>,
+                    input_mode: Res<InputMode>,
+                    gamepads: Query<&Gamepad>,
+                    mut taps: EventReader<Pointer<Down>>,
+                    mut keys: ResMut<ButtonInput<KeyCode>>,
+                    mut pressed: Local<Vec<KeyCode>>)
+{
+    for key_code in pressed.drain(..)
+    {
+        keys.release(key_code);
+    }
+
+    for gamepad in &gamepads
+    {
+        for button in gamepad.get_just_pressed()
+        {
+            if let Some(key_code) = menu_key_for_button(manager.curr_state, *button)
+            {
+                keys.press(key_code);
+                pressed.push(key_code);
+            }
+        }
+    }
+
+    // One tap is enough, however many entities it landed on.
+    let tapped = taps.read().any(|tap| tap.button == PointerButton::Primary);
+    if let Some(key_code) = menu_key_for_tap(manager.curr_state).filter(|_| tapped && *input_mode == InputMode::Pointer)
+    {
+        keys.press(key_code);
+        pressed.push(key_code);
+    }
+}
+
+/// In Mouse/Touch mode, clicking or tapping a mole presses the keys it is bound to. The presses
+/// go through the same path as real ones, so they are recorded in replays and a tap on an
+/// empty hole is a miss like any wrong key.
+fn whack_tapped_moles(manager: Res<GameManager>,
+                        input_mode: Res<InputMode>,
+                        moles: Query<&Mole>,
+                        mut taps: EventReader<Pointer<Down>>,
+                        mut presses: ResMut<KeyPresses>)
+{
+    if manager.curr_state != RoundState::Round || *input_mode != InputMode::Pointer
+    {
+        taps.clear();
+        return;
+    }
+
+    for tap in taps.read().filter(|tap| tap.button == PointerButton::Primary)
+    {
+        let Ok(mole) = moles.get(tap.target) else
+        {
+            continue;
+        };
+
+        presses.push(KeyPress { binding: mole.kill_key, glyph: Some(mole.glyph) });
+        for (binding, glyph) in &mole.chord
+        {
+            presses.push(KeyPress { binding: *binding, glyph: Some(*glyph) });
+        }
+    }
+}
+
+/// Swap the mouse cursor for a hammer in Mouse/Touch mode.
+fn update_cursor(mut commands: Commands,
+                    input_mode: Res<InputMode>,
+                    textures: Res<TextureAssets>,
+                    windows: Query<Entity, With<PrimaryWindow>>)
+{
+    let cursor = match *input_mode
+    {
+        InputMode::Pointer => CursorIcon::Custom(CustomCursor::Image { handle: textures.hammer_cursor.clone(), hotspot: (2, 2) }),
+        InputMode::Physical | InputMode::Logical => CursorIcon::System(SystemCursorIcon::Default),
+    };
+
+    for window in &windows
+    {
+        commands.entity(window).insert(cursor.clone());
+    }
+}
+
+fn is_not_paused(manager: Res<GameManager>) -> bool
+{
+    return manager.curr_state != RoundState::Paused;
+}
+
+/// Advance the round clock. Runs in `FixedUpdate`, so `time` is the fixed timestep.
+fn tick_round(time: Res<Time>, mut manager: ResMut<GameManager>)
+{
+    if manager.curr_state == RoundState::Round
+    {
+        manager.time_since_round_start.tick(time.delta());
+        manager.power_ups.tick(time.delta_secs());
+    }
+}
+
+fn start_round(manager: &mut GameManager, rng: &mut GameRng, seed: u64, mode: GameMode, max_health: i32,
+                moles: &mut Query<(&mut SpriteAnimator, &mut Mole)>)
+{
+    rng.reseed(seed);
+    manager.start_round(mode, max_health);
+    if mode == GameMode::Word
+    {
+        let glyphs: HashSet<char> = moles.iter().map(|(_, mole)| mole.glyph).collect();
+        manager.words = spellable_words(&glyphs);
+    }
+
+    for (mut mole_sprite, mut mole) in moles.iter_mut()
+    {
+        mole.status = MoleState::Hidden;
+        mole.kind = MoleKind::Normal;
+        mole.armor = 0;
+        mole.chord_press_times.fill(None);
+        mole.timer.reset();
+        mole.rng = mole_rng(rng, mole.hole_idx);
+        mole.reset_mole_time();
+        mole_sprite.play_anim(MOLE_HIDE_ANIM);
+    }
+}
+
+/// Record live key presses into the replay, or swap them for the replay's presses when playing one back.
+fn update_replay(manager: Res<GameManager>,
+                    mut presses: ResMut<KeyPresses>,
+                    mut recorder: ResMut<ReplayRecorder>,
+                    mut player: ResMut<ReplayPlayer>)
+{
+    if manager.curr_state != RoundState::Round
+    {
+        return;
+    }
+
+    let time = manager.time_since_round_start.elapsed_secs();
+    if player.is_playing()
+    {
+        presses.clear();
+        for press in player.presses_until(time)
+        {
+            presses.push(press);
+        }
+    }
+    else
+    {
+        for press in presses.iter()
+        {
+            recorder.record(time, press);
+        }
+    }
+}
+
+fn current_board<'a>(selected_board: &SelectedBoard,
+                        board_assets: &BoardAssets,
+                        boards: &'a Assets<BoardDefinition>) -> Option<&'a BoardDefinition>
+{
+    return board_assets.boards.get(selected_board.idx).and_then(|handle| boards.get(handle));
+}
+
+/// Index in `BoardAssets` of the board called `name`.
+fn board_index(board_assets: &BoardAssets, boards: &Assets<BoardDefinition>, name: &str) -> Option<usize>
+{
+    return board_assets.boards.iter().position(|handle| boards.get(handle).is_some_and(|board| board.name == name));
+}
+
+fn key_code_to_digit(key_code: KeyCode) -> Option<char>
+{
+    let digit = match key_code
+    {
+        KeyCode::Digit0 | KeyCode::Numpad0 => '0',
+        KeyCode::Digit1 | KeyCode::Numpad1 => '1',
+        KeyCode::Digit2 | KeyCode::Numpad2 => '2',
+        KeyCode::Digit3 | KeyCode::Numpad3 => '3',
+        KeyCode::Digit4 | KeyCode::Numpad4 => '4',
+        KeyCode::Digit5 | KeyCode::Numpad5 => '5',
+        KeyCode::Digit6 | KeyCode::Numpad6 => '6',
+        KeyCode::Digit7 | KeyCode::Numpad7 => '7',
+        KeyCode::Digit8 | KeyCode::Numpad8 => '8',
+        KeyCode::Digit9 | KeyCode::Numpad9 => '9',
+        _ => return None,
+    };
+
+    return Some(digit);
+}
+
+/// Longest seed that can be typed in. Anything longer would not fit in a u64.
+const MAX_SEED_LEN: usize = 19;
+const SEED_GLYPHS: &str = "0123456789";
+const NAME_GLYPHS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// Edit `entry` with the arrow keys, for players on a d-pad: right adds a character, left deletes
+/// one, and up and down step the last one through `glyphs`.
+fn edit_with_arrows(keys: &ButtonInput<KeyCode>, entry: &mut String, glyphs: &str, max_len: usize)
+{
+    let first = glyphs.chars().next().unwrap_or('0');
+    if keys.just_pressed(KeyCode::ArrowRight) && entry.chars().count() < max_len
+    {
+        entry.push(first);
+    }
+    else if keys.just_pressed(KeyCode::ArrowLeft)
+    {
+        entry.pop();
+    }
+    else if keys.just_pressed(KeyCode::ArrowUp) || keys.just_pressed(KeyCode::ArrowDown)
+    {
+        let Some(last) = entry.pop() else
+        {
+            entry.push(first);
+            return;
+        };
+
+        let num_glyphs = glyphs.chars().count();
+        let idx = glyphs.chars().position(|glyph| glyph == last).unwrap_or(0);
+        let idx = if keys.just_pressed(KeyCode::ArrowUp) { (idx + 1) % num_glyphs } else { (idx + num_glyphs - 1) % num_glyphs };
+        entry.push(glyphs.chars().nth(idx).unwrap_or(first));
+    }
+}
+
+#[derive(Component)]
+struct Healthbar;
+
+#[derive(Component)]
+struct Logo;
+
+#[derive(Component)]
+struct Heart;
+
+#[derive(Component)]
+struct LeaderboardText;
+
+/// Power-ups in effect, shown next to the hearts.
+#[derive(Component)]
+struct PowerUpText;
+
+fn update_power_up_text(game_manager: Res<GameManager>, mut query: Query<(&mut Text2d, &mut Visibility), With<PowerUpText>>)
+{
+    for (mut text, mut vis) in &mut query
+    {
+        let in_round = game_manager.curr_state == RoundState::Round || game_manager.curr_state == RoundState::Paused;
+        *vis = if in_round { Visibility::Visible } else { Visibility::Hidden };
+        *text = Text2d::new(game_manager.power_ups.describe());
+    }
+}
+
+/// Time left in a round against the clock, shown where the hearts would be.
+#[derive(Component)]
+struct Countdown;
+
+fn update_countdown(game_manager: Res<GameManager>, mut query: Query<(&mut Text2d, &mut Visibility), With<Countdown>>)
+{
+    for (mut text, mut vis) in &mut query
+    {
+        let in_round = game_manager.curr_state == RoundState::Round || game_manager.curr_state == RoundState::Paused;
+        match game_manager.time_left()
+        {
+            Some(time_left) if in_round =>
+            {
+                *vis = Visibility::Visible;
+                *text = Text2d::new(format!("{:.0}", time_left.ceil()));
+            }
+            _ => *vis = Visibility::Hidden,
+        }
+    }
+}
+
+/// Live scores of everyone in an online race.
+#[derive(Component)]
+struct RaceText;
+
+fn update_race_text(game_manager: Res<GameManager>,
+                    mode: Res<GameMode>,
+                    client: Res<RaceClient>,
+                    mut query: Query<(&mut Text2d, &mut Visibility), With<RaceText>>)
+{
+    for (mut text, mut vis) in &mut query
+    {
+        let race_screen = matches!(game_manager.curr_state, RoundState::Lobby | RoundState::Round | RoundState::Paused | RoundState::GameOver);
+        if *mode != GameMode::Race || !race_screen || client.standings.is_empty()
+        {
+            *vis = Visibility::Hidden;
+            continue;
+        }
+
+        *vis = Visibility::Visible;
+
+        let mut race_str = String::from("Race\n");
+        for (idx, entry) in client.standings.iter().enumerate()
+        {
+            let finished_str = if entry.finished { " - done" } else { "" };
+            race_str += &format!("\n{}. {} {}{}", idx + 1, entry.name, entry.score, finished_str);
+        }
+
+        *text = Text2d::new(race_str);
     }
 }
 
-impl Default for GameManager
+/// Darkens the board while the round is paused.
+#[derive(Component)]
+struct PauseOverlay;
+
+fn update_pause_overlay(game_manager: Res<GameManager>, mut overlays: Query<&mut Visibility, With<PauseOverlay>>)
 {
-    fn default() -> Self
+    for mut vis in &mut overlays
     {
-        return GameManager::new();
+        *vis = if game_manager.curr_state == RoundState::Paused { Visibility::Visible } else { Visibility::Hidden };
     }
 }
 
-fn update_gamemanager(time: Res<Time>, keys: Res<ButtonInput<KeyCode>>, 
-                        mut manager: ResMut<GameManager>,
-                        mut moles: Query<(&mut SpriteAnimator, &mut Mole)>)
+/// Show the high scores for the selected board on the title screen.
+fn update_leaderboard_text(game_manager: Res<GameManager>,
+                            leaderboard: Res<Leaderboard>,
+                            mode: Res<GameMode>,
+                            selected_board: Res<SelectedBoard>,
+                            board_assets: Res<BoardAssets>,
+                            boards: Res<Assets<BoardDefinition>>,
+                            mut query: Query<(&mut Text2d, &mut Visibility), With<LeaderboardText>>)
 {
-    match manager.curr_state 
+    for (mut text, mut vis) in &mut query
     {
-        RoundState::Begin =>
-        {
-            if keys.just_pressed(KeyCode::Enter) || keys.just_pressed(KeyCode::Space)
-            {
-                manager.start_round();
-
-                for (mut mole_sprite, mut mole) in &mut moles
-                {
-                    mole.status = MoleState::Hidden;
-                    mole_sprite.play_anim(MOLE_HIDE_ANIM);
-                }
-            }
-        }
-        RoundState::Round =>
+        if game_manager.curr_state != RoundState::Begin || !mode.has_leaderboard()
         {
-            manager.time_since_round_start.tick(time.delta());
+            *vis = Visibility::Hidden;
+            continue;
         }
-        RoundState::GameOver =>
+
+        *vis = Visibility::Visible;
+
+        let board_name = current_board(&selected_board, &board_assets, &boards).map_or("", |board| board.name.as_str());
+        let mut scores_str = format!("High Scores\n{} - {}\n", mode.name(), board_name);
+        for (idx, entry) in leaderboard.top(mode.name(), board_name).iter().enumerate()
         {
-            if keys.just_pressed(KeyCode::Enter)
-            {
-                manager.curr_state = RoundState::Begin;
-            }
+            scores_str += &format!("\n{}. {} {}", idx + 1, entry.name, entry.score);
         }
+
+        *text = Text2d::new(scores_str);
     }
 }
 
-#[derive(Component)]
-struct Healthbar;
-
-#[derive(Component)]
-struct Logo;
-
-#[derive(Component)]
-struct Heart;
+/// Options shown on the title screen, read-only counterpart of `RoundOptions`.
+#[derive(SystemParam)]
+struct TitleOptions<'w>
+{
+    mode: Res<'w, GameMode>,
+    drill: Res<'w, KeyDrill>,
+    layouts: Res<'w, KeyboardLayouts>,
+    input_mode: Res<'w, InputMode>,
+    selected_board: Res<'w, SelectedBoard>,
+    board_assets: Res<'w, BoardAssets>,
+    boards: Res<'w, Assets<BoardDefinition>>,
+    curves: Res<'w, DifficultyCurves>,
+}
 
 fn update_healthbar(game_manager: Res<GameManager>,
+            options: TitleOptions,
+            rng: Res<GameRng>,
+            player: Res<ReplayPlayer>,
+            settings: Res<Settings>,
+            settings_menu: Res<SettingsMenu>,
+            level_progress: Res<LevelProgress>,
+            race_client: Res<RaceClient>,
             mut healthbar: Query<(&mut Healthbar, &mut Children)>, 
             mut hb_sprites: Query<(&mut Sprite, &mut Visibility), (With<Heart>, Without<Logo>)>,
             mut hb_logos: Query<(&mut Sprite, &mut Visibility), (With<Logo>, Without<Heart>)>,
-            mut hb_title: Query<&mut Text2d>)
+            mut hb_title: Query<&mut Text2d, (Without<Countdown>, Without<PowerUpText>)>)
 {
     let mut heart_idx = 0;
     
@@ -136,15 +1316,31 @@ fn update_healthbar(game_manager: Res<GameManager>,
             if let Ok(hb_sprite) = hb_sprites.get_mut(*child)
             {
                 let (mut sprite, mut vis) = hb_sprite;
-                if game_manager.curr_state != RoundState::Round
+                let in_round = game_manager.curr_state == RoundState::Round || game_manager.curr_state == RoundState::Paused;
+                if !in_round || !game_manager.mode.uses_hearts()
                 {
                     *vis = Visibility::Hidden;
                 }
+                else if game_manager.mode == GameMode::Versus
+                {
+                    // The left half of the healthbar is the left player's, the right half the right player's.
+                    let (player, player_heart_idx) = ((heart_idx / (MAX_HEARTS / 2)) as usize, heart_idx % (MAX_HEARTS / 2));
+                    let alive = player_heart_idx < game_manager.versus[player].health(game_manager.max_health);
+                    *vis = if player_heart_idx < game_manager.max_health { Visibility::Visible } else { Visibility::Hidden };
+                    if let Some(atlas) = &mut sprite.texture_atlas
+                    {
+                        atlas.index = if alive { 0 } else { 1 };
+                    }
+                }
                 else if let Some(atlas) = &mut sprite.texture_atlas
                 {
                     *vis = Visibility::Visible;
                     let alive : bool = heart_idx < game_manager.get_curr_health();
                     atlas.index = if alive { 0 } else { 1 };
+                    if heart_idx >= game_manager.max_health
+                    {
+                        *vis = Visibility::Hidden;
+                    }
                 }
                 else
                 {
@@ -156,7 +1352,7 @@ fn update_healthbar(game_manager: Res<GameManager>,
             else if let Ok(logo) = hb_logos.get_mut(*child)
             {
                 let (_sprite, mut vis) = logo;
-                if game_manager.curr_state == RoundState::Round || game_manager.curr_state == RoundState::GameOver
+                if game_manager.curr_state != RoundState::Begin
                 {
                     *vis = Visibility::Hidden;
                 }
@@ -169,16 +1365,120 @@ fn update_healthbar(game_manager: Res<GameManager>,
             {
                 if game_manager.curr_state == RoundState::Begin
                 {
-                    *hb_title = Text2d::new("Press [Enter]");
+                    let board_name = current_board(&options.selected_board, &options.board_assets, &options.boards).map_or("", |board| board.name.as_str());
+                    let difficulty_str = settings.adaptive_target.map_or(options.curves.current().name().to_string(),
+                                                                        |target| format!("Adaptive {:.0}%", target * 100.0));
+                    let mode_str = if *options.mode == GameMode::Zen { format!("{}\n[F7] Drill: {}", options.mode.name(), options.drill.name()) } else { String::from(options.mode.name()) };
+                    let seed_str = if game_manager.seed_entry.is_empty() { "Random" } else { game_manager.seed_entry.as_str() };
+                    let title_str = format!("Press [Enter]\n\n[Tab] Layout: {}\n[F1] Keys: {}\n[F6] Mode: {}\n[F2] Board: {}\n[F5] Difficulty: {}\n[0-9/Arrows] Seed: {}\n[F3] Watch last replay\n[F4] Settings",
+                                            options.layouts.current().name(), options.input_mode.name(), mode_str, board_name, difficulty_str, seed_str);
+                    *hb_title = Text2d::new(title_str);
+                }
+                else if game_manager.curr_state == RoundState::Settings
+                {
+                    let menu_str = format!("{}\n\n[Arrows] Change  [Enter] Back", settings_menu.describe(&settings));
+                    *hb_title = Text2d::new(menu_str);
+                }
+                else if game_manager.curr_state == RoundState::LevelSelect
+                {
+                    let mut levels_str = String::from("SELECT LEVEL\n");
+                    for (idx, level) in LEVELS.iter().enumerate()
+                    {
+                        let cursor = if idx == game_manager.level_cursor { ">" } else { " " };
+                        if level_progress.is_unlocked(idx)
+                        {
+                            levels_str += &format!("\n{} {}. {} - {}", cursor, idx + 1, level.name, level.board);
+                        }
+                        else
+                        {
+                            levels_str += &format!("\n{} {}. Locked", cursor, idx + 1);
+                        }
+                    }
+                    levels_str += "\n\n[Arrows] Choose  [Enter] Play  [Esc] Back";
+                    *hb_title = Text2d::new(levels_str);
+                }
+                else if game_manager.curr_state == RoundState::Lobby
+                {
+                    let lobby_str = match &race_client.error
+                    {
+                        Some(err) => format!("RACE LOBBY\n\n Could not reach the relay at\n {}\n {}\n\n[Esc] Back", settings.relay_address, err),
+                        None if race_client.lobby.is_empty() => format!("RACE LOBBY\n\n Waiting for the relay at\n {}\n\n[Esc] Back", settings.relay_address),
+                        None => format!("RACE LOBBY\n Players:\n {}\n\n[Enter] Start race  [Esc] Leave", race_client.lobby.join("\n ")),
+                    };
+                    *hb_title = Text2d::new(lobby_str);
+                }
+                else if game_manager.curr_state == RoundState::LevelComplete
+                {
+                    let level = game_manager.level.unwrap_or_default();
+                    let (title_str, next_str) = if level + 1 < LEVELS.len() { ("LEVEL COMPLETE", "Next level") } else { ("ALL LEVELS COMPLETE", "Levels") };
+                    let complete_str = format!("{}\n Level {}: {}  Score: {}\n {}\n\n[Enter] {}  [Esc] Levels\n ",
+                                                title_str, level + 1, LEVELS[level].name, game_manager.score(),
+                                                game_manager.scoring.breakdown(), next_str);
+                    *hb_title = Text2d::new(complete_str);
                 }
                 else if game_manager.curr_state == RoundState::Round
                 {
-                    let score_str = format!("Score: {}\n\n\n\n\n\n ", game_manager.moles_hit);
+                    let replay_str = if player.is_playing() { "REPLAY - " } else { "" };
+                    let word_str = match (&game_manager.word, game_manager.level)
+                    {
+                        (Some(word), _) if game_manager.mode == GameMode::Word => word.display(),
+                        (_, Some(_)) if game_manager.boss.is_some() => String::from("BOSS WAVE!"),
+                        (_, Some(level)) => format!("Level {}: {}  Target: {}", level + 1, LEVELS[level].name, LEVELS[level].target_score),
+                        _ => String::new(),
+                    };
+                    let combo_str = if game_manager.scoring.multiplier() > 1 { format!("  x{}", game_manager.scoring.multiplier()) } else { String::new() };
+                    let score_str = format!("{}{}{}\n{}\n\n\n\n\n ", replay_str, game_manager.score_str(), combo_str, word_str);
                     *hb_title = Text2d::new(score_str);
                 }
+                else if game_manager.curr_state == RoundState::Paused
+                {
+                    let quit_str = if game_manager.mode == GameMode::Zen { "Finish" } else { "Quit" };
+                    let pause_str = format!("PAUSED\n {}\n\n[Esc] Resume\n[R] Restart\n[Q] {}\n ", game_manager.score_str(), quit_str);
+                    *hb_title = Text2d::new(pause_str);
+                }
                 else if game_manager.curr_state == RoundState::GameOver
                 {
-                    let score_str = format!("GAME OVER\n Score: {}\n\n\n\n ", game_manager.moles_hit);
+                    let name_str = match &game_manager.name_entry
+                    {
+                        Some(name) => format!("New high score!\n Name: {}_", name),
+                        None => String::from("\n "),
+                    };
+                    let score_str = match game_manager.mode
+                    {
+                        GameMode::Classic => format!("GAME OVER\n Score: {}\n {}\n Seed: {}\n{}\n ",
+                                                    game_manager.score(), game_manager.scoring.breakdown(), rng.seed(), name_str),
+                        GameMode::Levels => format!("GAME OVER\n Level {}: {}  Score: {}\n {}\n Seed: {}\n{}\n ",
+                                                    game_manager.level.map_or(0, |level| level + 1),
+                                                    game_manager.level.map_or("", |level| LEVELS[level].name),
+                                                    game_manager.score(), game_manager.scoring.breakdown(), rng.seed(), name_str),
+                        GameMode::Word => format!("GAME OVER\n Score: {}  Word bonus: {}\n {}\n Seed: {}\n{}\n ",
+                                                    game_manager.score(), game_manager.word_bonus, game_manager.scoring.breakdown(), rng.seed(), name_str),
+                        GameMode::Blitz => format!("TIME UP\n Score: {}\n {}\n Wrong: {}  Escaped: {}\n Seed: {}\n{}\n ",
+                                                    game_manager.score(), game_manager.scoring.breakdown(),
+                                                    game_manager.wrong_keys, game_manager.moles_missed - game_manager.wrong_keys,
+                                                    rng.seed(), name_str),
+                        GameMode::Race => format!("RACE OVER\n Place: {}  Score: {}\n {}\n Wrong: {}  Escaped: {}\n Seed: {}\n\n ",
+                                                    race_client.place().map_or(String::from("-"), |place| format!("{} of {}", place, race_client.standings.len())),
+                                                    game_manager.score(), game_manager.scoring.breakdown(),
+                                                    game_manager.wrong_keys, game_manager.moles_missed - game_manager.wrong_keys, rng.seed()),
+                        GameMode::Versus =>
+                        {
+                            let winner_str = game_manager.winner().map_or(String::from("DRAW"), |side| format!("{} PLAYER WINS", side.name().to_uppercase()));
+                            let [left, right] = &game_manager.versus;
+                            format!("{}\n {}\n Hits: {} / {}  Best combo: {} / {}\n Seed: {}\n\n ",
+                                    winner_str, game_manager.score_str(), left.moles_hit, right.moles_hit,
+                                    left.scoring.best_combo, right.scoring.best_combo, rng.seed())
+                        }
+                        GameMode::Zen =>
+                        {
+                            let weakest_str = game_manager.weakest_keys(5).iter()
+                                                .map(|(glyph, stats)| format!("{} {:.0}%", glyph, stats.accuracy() * 100.0))
+                                                .collect::<Vec<String>>()
+                                                .join("  ");
+                            format!("PRACTICE OVER\n Hits: {}  Misses: {}\n Weakest keys:\n {}\n Seed: {}\n\n ",
+                                    game_manager.moles_hit, game_manager.moles_missed, weakest_str, rng.seed())
+                        }
+                    };
                     *hb_title = Text2d::new(score_str);
                 }
             }
@@ -186,6 +1486,101 @@ fn update_healthbar(game_manager: Res<GameManager>,
     }
 }
 
+// =============================================
+// SCORE POPUPS
+// =============================================
+const POPUP_LIFETIME: f32 = 0.8;
+const POPUP_RISE_SPEED: f32 = 60.0;
+
+/// Points scored by a hit, to float up above the mole that was hit.
+#[derive(Event)]
+struct ScorePopupEvent
+{
+    position: Vec2,
+    /// Points before the multiplier, so "+2 x3" is worth 6.
+    points: i32,
+    multiplier: i32,
+}
+
+#[derive(Component)]
+struct ScorePopup
+{
+    timer: Timer,
+}
+
+fn spawn_score_popups(mut commands: Commands, fonts: Res<FontAssets>, mut events: EventReader<ScorePopupEvent>)
+{
+    for event in events.read()
+    {
+        let text = if event.multiplier > 1 { format!("+{} x{}", event.points, event.multiplier) } else { format!("+{}", event.points) };
+        commands.spawn((Text2d::new(text),
+                        TextFont { font: fonts.pixica.clone(),
+                                    font_size: 32.0,
+                                    ..default()}
+                                .with_font_smoothing(FontSmoothing::None),
+                        TextColor(Color::srgb(1.0, 0.9, 0.2)),
+                        Transform::from_xyz(event.position.x, event.position.y + 40.0, 3.0),
+                        ScorePopup { timer: Timer::from_seconds(POPUP_LIFETIME, TimerMode::Once) }));
+    }
+}
+
+fn animate_score_popups(mut commands: Commands,
+                        time: Res<Time>,
+                        mut query: Query<(Entity, &mut ScorePopup, &mut Transform, &mut TextColor)>)
+{
+    for (entity, mut popup, mut transform, mut color) in &mut query
+    {
+        popup.timer.tick(time.delta());
+        if popup.timer.finished()
+        {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        transform.translation.y += POPUP_RISE_SPEED * time.delta_secs();
+        color.0.set_alpha(1.0 - popup.timer.fraction());
+    }
+}
+
+// =============================================
+// HAMMER
+// =============================================
+const HAMMER_HIT_ANIM: &str = "HammerHit";
+const HAMMER_WHIFF_ANIM: &str = "HammerWhiff";
+
+/// From the hammer sprite's center to where its head lands, so the head comes down on the mole.
+const HAMMER_OFFSET: Vec2 = Vec2::new(24.0, 24.0);
+
+/// A hole was whacked, with or without a mole up in it.
+#[derive(Event)]
+struct HammerSwingEvent
+{
+    position: Vec2,
+    /// Whether the swing whacked a mole worth hitting, which shows the sparks.
+    hit: bool,
+}
+
+#[derive(Component)]
+struct Hammer;
+
+/// Move the hammer to the hole just whacked and swing it, throwing sparks on a hit and dust on a whiff.
+/// Its animations end on an empty frame, so it is only seen mid-swing.
+fn swing_hammer(mut events: EventReader<HammerSwingEvent>, mut query: Query<(&mut SpriteAnimator, &mut Transform), With<Hammer>>)
+{
+    // Only the last swing of the step shows, since there is just the one hammer.
+    let Some(swing) = events.read().last() else
+    {
+        return;
+    };
+
+    for (mut animator, mut transform) in &mut query
+    {
+        let pos = swing.position + HAMMER_OFFSET;
+        transform.translation = Vec3::new(pos.x, pos.y, transform.translation.z);
+        animator.play_anim(if swing.hit { HAMMER_HIT_ANIM } else { HAMMER_WHIFF_ANIM });
+    }
+}
+
 #[derive(Resource, Clone)]
 struct ScreenShaker
 {
@@ -221,9 +1616,9 @@ impl Default for ScreenShaker
     }
 }
 
-fn handle_shake(time: Res<Time>, mut shaker: ResMut<ScreenShaker>, mut query: Query<(&mut Camera2d, &mut Transform)>)
+fn handle_shake(time: Res<Time>, settings: Res<Settings>, mut shaker: ResMut<ScreenShaker>, mut query: Query<(&mut Camera2d, &mut Transform)>)
 {
-    let delta = shaker.get_delta();
+    let delta = shaker.get_delta() * settings.shake_intensity;
     shaker.shake_timer.tick(time.delta());
 
     for (_, mut trans) in &mut query
@@ -266,7 +1661,9 @@ impl SpriteAnimation
 struct SpriteAnimator
 {
     animations: HashMap<String, SpriteAnimation>,
-    curr_playing_idx: Option<String>
+    curr_playing_idx: Option<String>,
+    /// Added to every frame played, to pick between sets of frames in the same atlas.
+    frame_offset: usize,
 }
 
 impl SpriteAnimator
@@ -276,7 +1673,8 @@ impl SpriteAnimator
         Self
         {
             animations: HashMap::new(),
-            curr_playing_idx: None
+            curr_playing_idx: None,
+            frame_offset: 0,
         }
     }
 
@@ -312,12 +1710,13 @@ fn animate_sprite(time: Res<Time>, mut query: Query<(&mut SpriteAnimator, &mut S
     for (mut animator, mut sprite) in &mut query
     {
         let mut anim_finished = false;
+        let frame_offset = animator.frame_offset;
         if let Some(animation) = animator.get_curr_anim()
         {
             // Update sprite
             if let Some(atlas) = &mut sprite.texture_atlas
             {
-                atlas.index = animation.anim_frames[animation.curr_frame_idx];
+                atlas.index = animation.anim_frames[animation.curr_frame_idx] + frame_offset;
             }
             else
             {
@@ -356,6 +1755,17 @@ const MOLE_RISE_ANIM: &str = "MoleUp";
 const MOLE_BONK_ANIM: &str = "MoleBonk";
 const MOLE_NOPE_ANIM: &str = "MoleNope";
 
+/// The mole atlas repeats its frames once per kind of mole, with the empty hole the same in each.
+const MOLE_FRAMES_PER_KIND: usize = 6;
+const MOLE_KINDS_IN_ATLAS: usize = 9;
+
+// Default (min, max) seconds a mole spends in each state, unless its hole overrides it.
+const MOLE_HIDDEN_TIME: (f32, f32) = (4.0, 14.0);
+const MOLE_UP_TIME: (f32, f32) = (3.0, 4.0);
+const MOLE_BONKED_TIME: (f32, f32) = (1.0, 2.0);
+/// Most seconds between the first and last key of a chord.
+const CHORD_WINDOW: f32 = 0.15;
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum MoleState
 {
@@ -364,22 +1774,167 @@ enum MoleState
     Bonked
 }
 
+/// Special moles start turning up `start` seconds into a round, and their weight
+/// grows to `max_weight` over the following `ramp` seconds.
+struct MoleKindSpawn
+{
+    kind: MoleKind,
+    start: f32,
+    ramp: f32,
+    max_weight: f32,
+}
+
+const NORMAL_MOLE_WEIGHT: f32 = 100.0;
+const SPECIAL_MOLE_SPAWNS: [MoleKindSpawn; 7] = [
+    MoleKindSpawn { kind: MoleKind::Golden, start: 10.0, ramp: 30.0, max_weight: 6.0 },
+    MoleKindSpawn { kind: MoleKind::Bomb, start: 20.0, ramp: 40.0, max_weight: 10.0 },
+    MoleKindSpawn { kind: MoleKind::Armored, start: 30.0, ramp: 30.0, max_weight: 12.0 },
+    MoleKindSpawn { kind: MoleKind::Decoy, start: 45.0, ramp: 30.0, max_weight: 8.0 },
+    MoleKindSpawn { kind: MoleKind::PowerUp(PowerUp::SlowTime), start: 20.0, ramp: 20.0, max_weight: 2.0 },
+    MoleKindSpawn { kind: MoleKind::PowerUp(PowerUp::Shield), start: 20.0, ramp: 20.0, max_weight: 2.0 },
+    MoleKindSpawn { kind: MoleKind::PowerUp(PowerUp::ExtraHeart), start: 30.0, ramp: 30.0, max_weight: 2.0 },
+];
+
+/// Extra points for hitting a golden mole, before the multiplier.
+const GOLDEN_MOLE_BONUS: i32 = 5;
+/// Golden moles stay up for this much of the usual time.
+const GOLDEN_MOLE_UP_SCALE: f32 = 0.5;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum MoleKind
+{
+    Normal,
+    /// Worth bonus points, but ducks back down quickly.
+    Golden,
+    /// Costs a heart if hit.
+    Bomb,
+    /// Needs hitting twice.
+    Armored,
+    /// Must be left alone: hitting it counts as a wrong key.
+    Decoy,
+    /// Hit it to collect the power-up.
+    PowerUp(PowerUp),
+}
+
+impl MoleKind
+{
+    /// Which set of frames in the mole atlas this kind is drawn with.
+    fn atlas_block(&self, armor: u8) -> usize
+    {
+        match self
+        {
+            MoleKind::Normal => 0,
+            MoleKind::Golden => 1,
+            MoleKind::Bomb => 2,
+            MoleKind::Armored if armor > 0 => 3,
+            MoleKind::Armored => 4,
+            MoleKind::Decoy => 5,
+            MoleKind::PowerUp(PowerUp::SlowTime) => 6,
+            MoleKind::PowerUp(PowerUp::Shield) => 7,
+            MoleKind::PowerUp(PowerUp::ExtraHeart) => 8,
+        }
+    }
+
+    /// Whether letting this mole go back down unhit is a miss.
+    fn must_hit(&self) -> bool
+    {
+        return !matches!(self, MoleKind::Bomb | MoleKind::Decoy | MoleKind::PowerUp(_));
+    }
+
+    /// Extra presses needed before the mole is bonked.
+    fn armor(&self) -> u8
+    {
+        return if *self == MoleKind::Armored { 1 } else { 0 };
+    }
+}
+
+/// Roll the kind of a mole coming up `elapsed_sec` into the round.
+fn pick_mole_kind(rng: &mut GameRng, elapsed_sec: f32, mode: GameMode) -> MoleKind
+{
+    let weight = |spawn: &MoleKindSpawn|
+    {
+        // Modes without hearts have none to restore.
+        if spawn.kind == MoleKind::PowerUp(PowerUp::ExtraHeart) && !mode.uses_hearts()
+        {
+            return 0.0;
+        }
+        return ((elapsed_sec - spawn.start) / spawn.ramp).clamp(0.0, 1.0) * spawn.max_weight;
+    };
+    let total_weight = NORMAL_MOLE_WEIGHT + SPECIAL_MOLE_SPAWNS.iter().map(weight).sum::<f32>();
+
+    let mut roll = rng.gen_range(0.0 .. total_weight);
+    for spawn in &SPECIAL_MOLE_SPAWNS
+    {
+        if roll < weight(spawn)
+        {
+            return spawn.kind;
+        }
+        roll -= weight(spawn);
+    }
+
+    return MoleKind::Normal;
+}
+
+/// Draw each kind of mole with its own frames, staying on the frame of whatever animation is playing.
+fn dress_moles(mut query: Query<(&Mole, &mut SpriteAnimator, &mut Sprite), Changed<Mole>>)
+{
+    for (mole, mut animator, mut sprite) in &mut query
+    {
+        animator.frame_offset = mole.kind.atlas_block(mole.armor) * MOLE_FRAMES_PER_KIND;
+        if let Some(atlas) = &mut sprite.texture_atlas
+        {
+            atlas.index = atlas.index % MOLE_FRAMES_PER_KIND + animator.frame_offset;
+        }
+    }
+}
+
+#[derive(Component)]
+struct MoleLabel;
+
+/// Each hole draws from its own stream of the round's seed, so what happens at one hole
+/// doesn't change what comes up at the others. Stream 0 is left to the round's own generator.
+fn mole_rng(rng: &GameRng, hole_idx: usize) -> GameRng
+{
+    return rng.stream(hole_idx as u64 + 1);
+}
+
 #[derive(Component)]
 struct Mole
 {
-    kill_key: KeyCode,
+    /// Index of this mole's hole on the board, used to update moles in a stable order.
+    hole_idx: usize,
+    kill_key: InputBinding,
+    glyph: char,
+    /// Keys that must be pressed along with `kill_key`, for chord moles.
+    chord: Vec<(InputBinding, char)>,
+    /// Round time each key of the chord (`kill_key` first) was last pressed.
+    chord_press_times: Vec<Option<f32>>,
     status: MoleState,
+    kind: MoleKind,
+    /// Presses left before an armored mole can be bonked.
+    armor: u8,
+    timing: HoleTiming,
+    /// This hole's own stream of the round's seed, which all its timers and kinds are drawn from.
+    rng: GameRng,
     timer: Timer
 }
 
 impl Mole
 {
-    fn new(key: KeyCode) -> Self
+    fn new(hole_idx: usize, key: InputBinding, glyph: char, chord: Vec<(InputBinding, char)>, timing: HoleTiming, rng: &GameRng) -> Self
     {
         let mut new_mole = Self
         {
+            hole_idx,
             kill_key: key,
+            glyph,
+            chord_press_times: vec![None; chord.len() + 1],
+            chord,
             status: MoleState::HeadUp,
+            kind: MoleKind::Normal,
+            armor: 0,
+            timing,
+            rng: mole_rng(rng, hole_idx),
             timer: Timer::new(Duration::from_secs(1), TimerMode::Repeating)
         };
 
@@ -388,15 +1943,61 @@ impl Mole
         return new_mole;
     }
 
+    /// Was this mole's key pressed this step? Chord moles need every key of the chord
+    /// pressed within `CHORD_WINDOW` seconds of each other. Presses are matched by round
+    /// time rather than by which keys are held, so chords replay the same as single keys.
+    fn is_pressed(&mut self, presses: &KeyPresses, mode: InputMode, round_time: f32) -> bool
+    {
+        if self.chord.is_empty()
+        {
+            return presses.just_pressed(mode, self.kill_key, self.glyph);
+        }
+
+        for idx in 0..self.chord_press_times.len()
+        {
+            let (binding, glyph) = if idx == 0 { (self.kill_key, self.glyph) } else { self.chord[idx - 1] };
+            if presses.just_pressed(mode, binding, glyph)
+            {
+                self.chord_press_times[idx] = Some(round_time);
+            }
+        }
+
+        let Some(times) = self.chord_press_times.iter().copied().collect::<Option<Vec<f32>>>() else
+        {
+            return false;
+        };
+
+        let first = times.iter().copied().fold(f32::MAX, f32::min);
+        let last = times.iter().copied().fold(f32::MIN, f32::max);
+        if last != round_time || last - first > CHORD_WINDOW
+        {
+            return false;
+        }
+
+        self.chord_press_times.fill(None);
+        return true;
+    }
+
+    /// Knock the mole down for a cooldown, scaled by the difficulty curve, before it can hide and rise again.
+    fn bonk(&mut self, cooldown_scale: f32)
+    {
+        self.status = MoleState::Bonked;
+        self.timer.reset();
+        self.reset_mole_time();
+
+        let cooldown = self.timer.duration().as_secs_f32() * cooldown_scale;
+        self.timer.set_duration(Duration::from_secs_f32(cooldown));
+    }
+
     fn reset_mole_time(&mut self)
     {
-        let mut rng = rand::thread_rng();
-        let duration = match self.status
+        let (min, max) = match self.status
         {
-            MoleState::Hidden => rng.gen_range(4.0 .. 14.0),
-            MoleState::HeadUp => rng.gen_range(3.0 .. 4.0),
-            MoleState::Bonked => rng.gen_range(1.0 .. 2.0),
+            MoleState::Hidden => self.timing.hidden.unwrap_or(MOLE_HIDDEN_TIME),
+            MoleState::HeadUp => self.timing.up.unwrap_or(MOLE_UP_TIME),
+            MoleState::Bonked => self.timing.bonked.unwrap_or(MOLE_BONKED_TIME),
         };
+        let duration = self.rng.gen_range(min ..= max);
 
         self.timer.set_duration(Duration::from_secs_f32(duration));
     }
@@ -405,10 +2006,18 @@ impl Mole
 fn update_moles(time: Res<Time>, 
                 audio_assets: Res<AudioAssets>, 
                 audio: Res<Audio>,
-                keys: Res<ButtonInput<KeyCode>>,
+                presses: Res<KeyPresses>,
+                input_mode: Res<InputMode>,
                 mut manager: ResMut<GameManager>,
                 mut shaker: ResMut<ScreenShaker>,
-                mut query: Query<(&mut SpriteAnimator, &mut Mole)>)
+                mut rng: ResMut<GameRng>,
+                settings: Res<Settings>,
+                curves: Res<DifficultyCurves>,
+                mut adaptive: ResMut<AdaptiveDifficulty>,
+                drill: Res<KeyDrill>,
+                mut popups: EventWriter<ScorePopupEvent>,
+                mut swings: EventWriter<HammerSwingEvent>,
+                mut query: Query<(&mut SpriteAnimator, &mut Mole, &Transform)>)
 {
     if manager.curr_state != RoundState::Round
     {
@@ -416,61 +2025,184 @@ fn update_moles(time: Res<Time>,
     }
 
     let elapsed_sec = manager.time_since_round_start.elapsed_secs();
-    let max_mole_up = (1.0 - (1.0 / (1.0 + elapsed_sec * 0.01))) * 18.0;
-    let total_mole_up = query.iter().filter(|(_, m)| m.status == MoleState::HeadUp).count() as f32;
+    // Adaptive difficulty, when turned on, takes over from the selected curve.
+    let curve: &dyn DifficultyCurve = if adaptive.target().is_some() { &*adaptive } else { curves.current() };
+    let max_mole_up = curve.max_moles_up(elapsed_sec);
+    let total_mole_up = query.iter().filter(|(_, m, _)| m.status == MoleState::HeadUp).count() as f32;
+    let mode = manager.mode;
+    // A mirrored pair of moles counts once in Versus mode.
+    let total_mole_up = if mode == GameMode::Versus { total_mole_up / 2.0 } else { total_mole_up };
+
+    let time_scale = manager.power_ups.time_scale();
+
+    // Word mode raises moles itself, one letter at a time.
+    let word_mode = manager.mode == GameMode::Word;
+    let mut word_mole_up = word_mode && total_mole_up > 0.0;
+
+    // During a boss wave only the wave's moles come up.
+    let boss_wave = manager.boss.is_some();
+    let boss_raises = manager.boss.as_mut().map_or(Vec::new(), |boss| boss.tick(time.delta_secs() * time_scale));
+
+    // Zen mode only lets moles up on the keys being drilled. Versus mode raises moles in mirrored
+    // pairs, led by the left-hand one, so both players always face the same moles.
+    let hidden_keys: HashSet<KeyCode> = query.iter().filter(|(_, m, _)| m.status == MoleState::Hidden).filter_map(|(_, m, _)| m.kill_key.key_code()).collect();
+    let may_rise = |binding: InputBinding| match (mode, binding.key_code())
+    {
+        (GameMode::Zen, Some(key_code)) => drill.includes(key_code),
+        (GameMode::Zen, None) => *drill == KeyDrill::AllKeys,
+        (GameMode::Versus, Some(key_code)) => Side::of_key(key_code) == Some(Side::Left)
+                                                && mirrored_key(key_code).is_some_and(|mirrored| hidden_keys.contains(&mirrored)),
+        (GameMode::Versus, None) => false,
+        _ => true,
+    };
+    // Keys of moles to raise after the loop as the partners of left-hand moles, with the timer to copy.
+    let mut mirrored_rises: Vec<(KeyCode, Timer)> = Vec::new();
 
-    for (mut animator, mut mole) in &mut query
+    // Reaction time of each hit, or None for each miss, fed to the adaptive difficulty once the curve is done with.
+    let mut outcomes: Vec<Option<f32>> = Vec::new();
+
+    for (mut animator, mut mole, transform) in query.iter_mut().sort_by_key::<&Mole, _>(|mole| mole.hole_idx)
     {
-        mole.timer.tick(time.delta());
+        mole.timer.tick(time.delta().mul_f32(time_scale));
         let mut prev_state = mole.status;
+        let side = if mode == GameMode::Versus { mole.kill_key.key_code().and_then(Side::of_key) } else { None };
 
         if elapsed_sec < 1.5
         {
             mole.status = MoleState::Hidden;
             prev_state = MoleState::Hidden;
         }
-        else if keys.just_pressed(mole.kill_key) 
+        else if mole.is_pressed(&presses, *input_mode, elapsed_sec)
         {
-            if mole.status == MoleState::HeadUp
+            // Bombs and decoys are mistakes, so they get a whiff rather than sparks.
+            let hit = mole.status == MoleState::HeadUp && !matches!(mole.kind, MoleKind::Bomb | MoleKind::Decoy);
+            swings.send(HammerSwingEvent { position: transform.translation.truncate(), hit });
+
+            if let (MoleState::HeadUp, MoleKind::PowerUp(power_up)) = (mole.status, mole.kind)
+            {
+                animator.play_anim(MOLE_BONK_ANIM);
+                mole.bonk(curve.bonk_cooldown_scale(elapsed_sec));
+                manager.activate_power_up(power_up);
+
+                audio.play(audio_assets.bonk.clone()).with_volume(settings.sfx_volume);
+            }
+            else if mole.status == MoleState::HeadUp && !mole.kind.must_hit()
+            {
+                // Bombs and decoys were meant to be left alone.
+                animator.play_anim(MOLE_BONK_ANIM);
+                mole.bonk(curve.bonk_cooldown_scale(elapsed_sec));
+                manager.add_mistake(true, side);
+                manager.record_key(mole.glyph, false);
+                outcomes.push(None);
+                shaker.shake_for(if mole.kind == MoleKind::Bomb { 0.6 } else { 0.4 });
+
+                audio.play(audio_assets.nope.clone()).with_volume(settings.sfx_volume * 0.5);
+            }
+            else if mole.status == MoleState::HeadUp && mole.armor > 0
+            {
+                mole.armor -= 1;
+                audio.play(audio_assets.bonk.clone()).with_volume(settings.sfx_volume * 0.5);
+            }
+            else if mole.status == MoleState::HeadUp
             {
+                // Taken before the bonk restarts the timer for the cooldown.
+                let reaction_sec = mole.timer.elapsed_secs();
                 animator.play_anim(MOLE_BONK_ANIM);
-                mole.status = MoleState::Bonked;
-                manager.moles_hit += 1;
+                mole.bonk(curve.bonk_cooldown_scale(elapsed_sec));
+                manager.record_key(mole.glyph, true);
+
+                let bonus = if mole.kind == MoleKind::Golden { GOLDEN_MOLE_BONUS } else { 0 };
+                let (points, multiplier) = manager.add_hit(reaction_sec, bonus, side);
+                popups.send(ScorePopupEvent { position: transform.translation.truncate(), points, multiplier });
+                if word_mode
+                {
+                    manager.advance_word();
+                    word_mole_up = false;
+                }
+                outcomes.push(Some(reaction_sec));
 
-                audio.play(audio_assets.bonk.clone()).with_volume(0.2);
+                audio.play(audio_assets.bonk.clone()).with_volume(settings.sfx_volume);
             }
             else if mole.status == MoleState::Hidden
             {
-                manager.moles_missed += 1;
+                manager.add_mistake(true, side);
+                manager.record_key(mole.glyph, false);
+                outcomes.push(None);
                 shaker.shake_for(0.4);
+                // Whatever came up here last is gone, so it's a plain mole that peeks out.
+                mole.kind = MoleKind::Normal;
                 animator.play_anim(MOLE_NOPE_ANIM);
 
                 mole.reset_mole_time();
 
-                audio.play(audio_assets.nope.clone()).with_volume(0.1);
+                audio.play(audio_assets.nope.clone()).with_volume(settings.sfx_volume * 0.5);
             }
         }
         else if mole.timer.just_finished()
         {
             mole.status = match mole.status 
             {
-                MoleState::Hidden => if total_mole_up < max_mole_up { MoleState::HeadUp } else { MoleState::Hidden },
+                MoleState::Hidden => if !word_mode && !boss_wave && total_mole_up < max_mole_up && may_rise(mole.kill_key) { MoleState::HeadUp } else { MoleState::Hidden },
                 MoleState::HeadUp => MoleState::Hidden,
                 MoleState::Bonked => MoleState::Hidden,
             };
 
+            if mole.status == MoleState::HeadUp
+            {
+                mole.kind = if manager.has_special_moles() { pick_mole_kind(&mut mole.rng, elapsed_sec, manager.mode) } else { MoleKind::Normal };
+                mole.armor = mole.kind.armor();
+            }
+
             // Do random something here.
             mole.reset_mole_time();
 
             // Adjust time to make game increasingly difficult.
             let new_dur = mole.timer.duration();
-            let diff_factor = 1.0 / (elapsed_sec * 0.01 + 2.0) + 0.5;
+            // Bonked moles only ever go back down here; their cooldown is scaled when they are bonked.
+            let diff_factor = if mole.status == MoleState::HeadUp { curve.up_time_scale(elapsed_sec) } else { curve.hidden_time_scale(elapsed_sec) };
+            let diff_factor = if mole.status == MoleState::HeadUp && mole.kind == MoleKind::Golden { diff_factor * GOLDEN_MOLE_UP_SCALE } else { diff_factor };
             mole.timer.set_duration(Duration::from_secs_f32(new_dur.as_secs_f32() * diff_factor));
+
+            if let Some(mirrored) = mole.kill_key.key_code().and_then(mirrored_key).filter(|_| mode == GameMode::Versus && mole.status == MoleState::HeadUp)
+            {
+                mirrored_rises.push((mirrored, mole.timer.clone()));
+            }
+        }
+
+        // Raise the mole for the next letter of the word. One that just escaped waits a step before coming back.
+        let next_letter = manager.word.as_ref().and_then(WordProgress::current);
+        if word_mode && !word_mole_up && elapsed_sec >= 1.5 && prev_state == MoleState::Hidden
+            && mole.status == MoleState::Hidden && next_letter == Some(mole.glyph)
+        {
+            mole.status = MoleState::HeadUp;
+            mole.timer.reset();
+            mole.reset_mole_time();
+            let up_dur = mole.timer.duration().as_secs_f32() * curve.up_time_scale(elapsed_sec);
+            mole.timer.set_duration(Duration::from_secs_f32(up_dur));
+            word_mole_up = true;
+        }
+
+        if boss_raises.contains(&mole.hole_idx) && mole.status != MoleState::HeadUp
+        {
+            // Moles still bonked are hurried back up, which the state change below doesn't animate.
+            if mole.status == MoleState::Bonked
+            {
+                animator.play_anim(MOLE_RISE_ANIM);
+            }
+            mole.status = MoleState::HeadUp;
+            mole.kind = MoleKind::Normal;
+            mole.armor = 0;
+            mole.timer.reset();
+            mole.reset_mole_time();
+            let up_dur = mole.timer.duration().as_secs_f32() * curve.up_time_scale(elapsed_sec);
+            mole.timer.set_duration(Duration::from_secs_f32(up_dur));
         }
 
-        if prev_state == MoleState::HeadUp && mole.status == MoleState::Hidden && elapsed_sec > 1.0
+        if prev_state == MoleState::HeadUp && mole.status == MoleState::Hidden && elapsed_sec > 1.0 && mole.kind.must_hit()
         { 
-            manager.moles_missed += 1;
+            manager.add_mistake(false, side);
+            manager.record_key(mole.glyph, false);
+            outcomes.push(None);
             shaker.shake_for(0.1);
         }
 
@@ -483,22 +2215,78 @@ fn update_moles(time: Res<Time>,
                 MoleState::Bonked => animator.play_anim(MOLE_BONK_ANIM),
             }
         }
+
+        // Half a chord pressed before the mole changed state doesn't carry over into the new one.
+        if prev_state != mole.status
+        {
+            mole.chord_press_times.fill(None);
+        }
+    }
+
+    for (mut animator, mut mole, _) in &mut query
+    {
+        if let Some((_, timer)) = mirrored_rises.iter().find(|(key_code, _)| Some(*key_code) == mole.kill_key.key_code())
+        {
+            mole.status = MoleState::HeadUp;
+            mole.kind = MoleKind::Normal;
+            mole.armor = 0;
+            mole.chord_press_times.fill(None);
+            mole.timer = timer.clone();
+            animator.play_anim(MOLE_RISE_ANIM);
+        }
+    }
+
+    if word_mode && manager.word.as_ref().is_none_or(WordProgress::is_finished)
+    {
+        let word = pick_word(&mut rng, &manager.words);
+        manager.word = word;
+    }
+
+    for outcome in outcomes
+    {
+        match outcome
+        {
+            Some(reaction_sec) => adaptive.record_hit(reaction_sec),
+            None => adaptive.record_miss(),
+        }
+    }
+
+    // Reaching a level's target score brings on its boss wave.
+    if let Some(level) = manager.level.filter(|level| manager.boss.is_none() && manager.score() >= LEVELS[*level].target_score)
+    {
+        let holes: Vec<(usize, KeyCode)> = query.iter().filter_map(|(_, mole, _)| Some((mole.hole_idx, mole.kill_key.key_code()?))).collect();
+        manager.boss = Some(BossWave::new(LEVELS[level].boss, &holes));
     }
 
-    if manager.get_curr_health() == 0
+    if manager.is_round_over()
     {
         manager.game_over();
-        for (mut animator, mut mole) in &mut query
+        for (mut animator, mut mole, _) in &mut query
         {
             mole.status = MoleState::HeadUp;
+            mole.kind = MoleKind::Normal;
             animator.play_anim(MOLE_RISE_ANIM);
         }
     }
+    else if manager.boss.as_ref().is_some_and(BossWave::is_done) && query.iter().all(|(_, mole, _)| mole.status != MoleState::HeadUp)
+    {
+        manager.curr_state = RoundState::LevelComplete;
+        for (mut animator, mut mole, _) in &mut query
+        {
+            if mole.status == MoleState::Bonked
+            {
+                mole.status = MoleState::Hidden;
+                animator.play_anim(MOLE_HIDE_ANIM);
+            }
+        }
+    }
 }
 
 // ====================================================
 // IN GAME PLUGIN
 // ====================================================
+const SIMULATION_HZ: f64 = 60.0;
+
 pub struct InGamePlugin;
 
 impl Plugin for InGamePlugin
@@ -509,16 +2297,66 @@ impl Plugin for InGamePlugin
                 // Startup
                 .init_resource::<GameManager>()
                 .init_resource::<ScreenShaker>()
+                .init_resource::<KeyboardLayouts>()
+                .init_resource::<SelectedBoard>()
+                .init_resource::<GameRng>()
+                .init_resource::<ReplayRecorder>()
+                .init_resource::<ReplayPlayer>()
+                .insert_resource(Leaderboard::load())
+                .insert_resource(Settings::load())
+                .init_resource::<SettingsMenu>()
+                .init_resource::<DifficultyCurves>()
+                .init_resource::<AdaptiveDifficulty>()
+                .init_resource::<GameMode>()
+                .init_resource::<KeyDrill>()
+                .insert_resource(LevelProgress::load())
+                .init_resource::<RaceClient>()
+                .add_event::<ScorePopupEvent>()
+                .add_event::<HammerSwingEvent>()
+                .insert_resource(Time::<Fixed>::from_hz(SIMULATION_HZ))
                 .add_systems(OnEnter(GameState::Playing), 
-                    setup_in_game)
+                    (setup_in_game, add_loaded_curves))
+
+                // Gamepad buttons and taps act as menu keys for every system reading the keyboard this frame,
+                // and tapped moles are pressed before the next fixed step.
+                .add_systems(PreUpdate,
+                    (press_menu_keys, whack_tapped_moles)
+                            .after(InputSystem)
+                            .after(PickSet::Last)
+                            .run_if(in_state(GameState::Playing)))
+
+                // Fixed update. All mole logic lives here so rounds play out the same at any frame rate.
+                .add_systems(FixedUpdate,
+                    (tick_round, update_replay, update_moles)
+                            .chain()
+                            .run_if(in_state(GameState::Playing)))
                 
                 // Update
                 .add_systems(Update, 
                     (update_gamemanager,
-                            animate_sprite,
+                            // Before the title screen, so a level picked this frame starts on its own board,
+                            // and the Enter that opens level select doesn't also pick a level.
+                            update_levels.before(rebuild_moles).before(update_gamemanager),
+                            update_race.before(rebuild_moles).before(update_gamemanager),
+                            // After the title screen, so the key that closes the menu doesn't also start a round.
+                            update_settings_menu.after(update_gamemanager),
+                            update_pause.after(update_gamemanager),
+                            animate_sprite.run_if(is_not_paused),
                             update_healthbar,
-                            update_moles,
-                            handle_shake)
+                            update_leaderboard_text,
+                            update_countdown,
+                            update_power_up_text,
+                            update_race_text,
+                            handle_shake.run_if(is_not_paused),
+                            update_pause_overlay,
+                            spawn_score_popups,
+                            swing_hammer.before(animate_sprite),
+                            dress_moles,
+                            update_cursor.run_if(resource_changed::<InputMode>),
+                            animate_score_popups.run_if(is_not_paused),
+                            rebuild_moles.run_if(resource_changed::<KeyboardLayouts>
+                                                    .or(resource_changed::<SelectedBoard>))
+                                        .before(update_gamemanager))
                             .run_if(in_state(GameState::Playing)));
         //app.add_systems(OnEnter(GameState::Playing), setup_in_game);
     }
@@ -536,9 +2374,6 @@ fn setup_in_game(mut commands: Commands,
     
     // Game BG
     commands.spawn(Sprite::from_image(textures.game_bg.clone()));
-    
-    // Create moles
-    create_all_moles(&mut commands, &mut texture_atlas_layouts, &textures, &fonts);
 
     // Create healthbar
     let text_font = TextFont { font: fonts.pixica.clone(),
@@ -562,9 +2397,26 @@ fn setup_in_game(mut commands: Commands,
                             logo_pos,
                             Visibility::Visible,
                             Logo))
+
+        .with_child((Text2d::new(""),
+                            text_font.clone()
+                                    .with_font_smoothing(FontSmoothing::None),
+                            Transform::from_xyz(0.0, 0.0, 1.0),
+                            Visibility::Hidden,
+                            Countdown))
+
+        .with_child((Text2d::new(""),
+                            TextFont { font: fonts.pixica.clone(),
+                                        font_size: 28.0,
+                                        ..default()}
+                                    .with_font_smoothing(FontSmoothing::None),
+                            TextColor(Color::srgb(0.4, 0.9, 1.0)),
+                            Transform::from_xyz(340.0, 0.0, 1.0),
+                            Visibility::Hidden,
+                            PowerUpText))
         .id();
 
-    for i in 0..MAX_MISTAKES
+    for i in 0..MAX_HEARTS
     {
         let mut transform = Transform::from_xyz(i as f32 * 50.0 - 225.0, 0.0, 1.0);
         transform.scale = Vec3::splat(2.0);
@@ -581,6 +2433,51 @@ fn setup_in_game(mut commands: Commands,
         commands.entity(hb).add_child(child);
     }
 
+    // High score table, shown on the title screen
+    commands.spawn((Text2d::new(""),
+                    TextFont { font: fonts.pixica.clone(),
+                                font_size: 28.0,
+                                ..default()}
+                            .with_font_smoothing(FontSmoothing::None),
+                    Transform::from_xyz(480.0, 180.0, 2.0),
+                    Visibility::Hidden,
+                    LeaderboardText));
+
+    // Live race scores, in the same spot since races have no high score table
+    commands.spawn((Text2d::new(""),
+                    TextFont { font: fonts.pixica.clone(),
+                                font_size: 28.0,
+                                ..default()}
+                            .with_font_smoothing(FontSmoothing::None),
+                    Transform::from_xyz(480.0, 180.0, 2.0),
+                    Visibility::Hidden,
+                    RaceText));
+
+    // Pause overlay, between the moles and their labels so the board stays readable
+    commands.spawn((Sprite::from_color(Color::srgba(0.0, 0.0, 0.0, 0.6), Vec2::new(1280.0, 720.0)),
+                    Transform::from_xyz(0.0, 0.0, 1.5),
+                    Visibility::Hidden,
+                    PauseOverlay));
+
+    // Hammer, which swings down on every hole whacked. It stays out of the way of clicks on the moles below.
+    let hammer_layout = TextureAtlasLayout::from_grid(UVec2::new(48, 48), 8, 1, None, None);
+    let mut hammer_animator = SpriteAnimator::new();
+    hammer_animator.push_anim(HAMMER_HIT_ANIM, SpriteAnimation::new( &vec![1,2,3,4,5,4,5,0], 0.04));
+    hammer_animator.push_anim(HAMMER_WHIFF_ANIM, SpriteAnimation::new( &vec![1,2,3,6,7,0], 0.05));
+
+    let mut hammer_pos = Transform::from_xyz(0.0, 0.0, 2.5);
+    hammer_pos.scale = Vec3::splat(2.0);
+    commands.spawn((Sprite::from_atlas_image(textures.hammer.clone(),
+                                            TextureAtlas
+                                            {
+                                                layout: texture_atlas_layouts.add(hammer_layout),
+                                                index: 0,
+                                            }),
+                    hammer_pos,
+                    hammer_animator,
+                    PickingBehavior::IGNORE,
+                    Hammer));
+
     // Load sounds
     // game_manager.music_handle = asset_server.load("MontyMoles.wav");
     // game_manager.bonk_handle = asset_server.load("Bonk.wav");
@@ -598,15 +2495,58 @@ fn setup_in_game(mut commands: Commands,
 
 }
 
+/// Offer the curves from `assets/difficulty` once they have loaded.
+fn add_loaded_curves(difficulty_assets: Res<DifficultyAssets>,
+                        loaded_curves: Res<Assets<PiecewiseCurve>>,
+                        mut curves: ResMut<DifficultyCurves>)
+{
+    for handle in &difficulty_assets.curves
+    {
+        if let Some(curve) = loaded_curves.get(handle)
+        {
+            curves.add(Box::new(curve.clone()));
+        }
+    }
+}
+
+/// Respawn every mole whenever the board or keyboard layout changes, since both move the holes.
+fn rebuild_moles(mut commands: Commands,
+                    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+                    textures: Res<TextureAssets>,
+                    fonts: Res<FontAssets>,
+                    layouts: Res<KeyboardLayouts>,
+                    selected_board: Res<SelectedBoard>,
+                    board_assets: Res<BoardAssets>,
+                    boards: Res<Assets<BoardDefinition>>,
+                    mut rng: ResMut<GameRng>,
+                    old_moles: Query<Entity, Or<(With<Mole>, With<MoleLabel>)>>)
+{
+    for entity in &old_moles
+    {
+        commands.entity(entity).despawn();
+    }
+
+    let Some(board) = current_board(&selected_board, &board_assets, &boards) else
+    {
+        warn!("No board loaded at index {}", selected_board.idx);
+        return;
+    };
+
+    create_all_moles(&mut commands, &mut texture_atlas_layouts, &textures, &fonts, board, layouts.current(), &mut rng);
+}
+
 fn create_all_moles(commands: &mut Commands, 
                     texture_atlas_layouts: &mut ResMut<Assets<TextureAtlasLayout>>, 
                     textures: &Res<TextureAssets>,
-                    fonts: &Res<FontAssets>)
+                    fonts: &Res<FontAssets>,
+                    board: &BoardDefinition,
+                    layout: &KeyboardLayout,
+                    rng: &mut GameRng)
 {
     // Setup moles
     let texture = textures.mole.clone();
-    let layout = TextureAtlasLayout::from_grid(UVec2::new(46, 37), 3, 2, Some(UVec2::new(1, 1)), None);
-    let texture_atlas_layout = texture_atlas_layouts.add(layout);
+    let atlas = TextureAtlasLayout::from_grid(UVec2::new(46, 37), 3, 2 * MOLE_KINDS_IN_ATLAS as u32, Some(UVec2::new(1, 1)), None);
+    let texture_atlas_layout = texture_atlas_layouts.add(atlas);
 
     let font = fonts.pixica.clone();
     let text_font = TextFont {
@@ -615,46 +2555,27 @@ fn create_all_moles(commands: &mut Commands,
         ..default()
     };
 
-    // Top row
-    create_mole_at(commands, Vec2::new(-460.0, -38.0) ,  KeyCode::KeyQ, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-364.0, -38.0) ,  KeyCode::KeyW, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-268.0, -38.0) ,  KeyCode::KeyE, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-172.0, -38.0) ,  KeyCode::KeyR, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-76.0, -38.0) ,  KeyCode::KeyT, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(20.0, -38.0) ,  KeyCode::KeyY, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(116.0, -38.0) ,  KeyCode::KeyU, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(212.0, -38.0) ,  KeyCode::KeyI, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(308.0, -38.0) ,  KeyCode::KeyO, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(404.0, -38.0), KeyCode::KeyP, &texture, &texture_atlas_layout, &text_font);
-
-    // Middle row
-    create_mole_at(commands, Vec2::new(-424.0, -112.0),  KeyCode::KeyA, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-328.0, -112.0),  KeyCode::KeyS, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-232.0, -112.0),  KeyCode::KeyD, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-136.0, -112.0),  KeyCode::KeyF, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-40.0, -112.0),  KeyCode::KeyG, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(56.0, -112.0),  KeyCode::KeyH, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(152.0, -112.0),  KeyCode::KeyJ, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(248.0, -112.0),  KeyCode::KeyK, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(344.0, -112.0),  KeyCode::KeyL, &texture, &texture_atlas_layout, &text_font);
-
-    // Bottom row
-    create_mole_at(commands, Vec2::new(-358.0, -186.0),  KeyCode::KeyZ, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-262.0, -186.0),  KeyCode::KeyX, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-166.0, -186.0),  KeyCode::KeyC, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(-70.0, -186.0),  KeyCode::KeyV, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(26.0, -186.0),  KeyCode::KeyB, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(122.0, -186.0),  KeyCode::KeyN, &texture, &texture_atlas_layout, &text_font);
-    create_mole_at(commands, Vec2::new(218.0, -186.0),  KeyCode::KeyM, &texture, &texture_atlas_layout, &text_font);
+    for (hole_idx, hole_def) in board.holes.iter().enumerate()
+    {
+        let Some(hole) = hole_def.resolve(layout) else
+        {
+            warn!("Board {} has a hole for {:?} which is not on the {} layout", board.name, hole_def.key, layout.name());
+            continue;
+        };
+
+        create_mole_at(commands, hole_idx, hole, &texture, &texture_atlas_layout, &text_font, rng);
+    }
 }
 
 fn create_mole_at(commands: &mut Commands, 
-                    mut pos: Vec2, key: KeyCode, 
+                    hole_idx: usize,
+                    hole: ResolvedHole,
                     texture: &Handle<Image>,  
                     texture_atlas_layout: &Handle<TextureAtlasLayout>,
-                    text_font: &TextFont)
+                    text_font: &TextFont,
+                    rng: &mut GameRng)
 {
-    pos += Vec2::new(46.0, -35.0);
+    let pos = hole.position + Vec2::new(46.0, -35.0);
     let mut mole_start = Transform::from_scale(Vec3::splat(2.0));
     mole_start.translation = Vec3::new(pos.x, pos.y, 1.0);
 
@@ -677,51 +2598,17 @@ fn create_mole_at(commands: &mut Commands,
         ),
         mole_start,
         anim_controller,
-        Mole::new(key)
+        Mole::new(hole_idx, hole.binding, hole.glyph, hole.chord, hole.timing, rng)
     ));    
 
     let font_pos = Vec3::new(pos.x - 22.0, pos.y + 18.0, 2.0);
-    let key_string = key_code_to_string(key);
     commands.spawn((
-        Text2d::new(key_string),
+        Text2d::new(hole.label),
         text_font
             .clone()
             .with_font_smoothing(FontSmoothing::None),
         Transform::from_translation(font_pos),
-        TextColor(Color::linear_rgb(0.1, 0.1, 0.1))
+        TextColor(Color::linear_rgb(0.1, 0.1, 0.1)),
+        MoleLabel
     ));
 }
-
-fn key_code_to_string(key_code: KeyCode) -> String
-{
-    match key_code
-    {
-        KeyCode::KeyA => String::from("A"),
-        KeyCode::KeyB => String::from("B"),
-        KeyCode::KeyC => String::from("C"),
-        KeyCode::KeyD => String::from("D"),
-        KeyCode::KeyE => String::from("E"),
-        KeyCode::KeyF => String::from("F"),
-        KeyCode::KeyG => String::from("G"),
-        KeyCode::KeyH => String::from("H"),
-        KeyCode::KeyI => String::from("I"),
-        KeyCode::KeyJ => String::from("J"),
-        KeyCode::KeyK => String::from("K"),
-        KeyCode::KeyL => String::from("L"),
-        KeyCode::KeyM => String::from("M"),
-        KeyCode::KeyN => String::from("N"),
-        KeyCode::KeyO => String::from("O"),
-        KeyCode::KeyP => String::from("P"),
-        KeyCode::KeyQ => String::from("Q"),
-        KeyCode::KeyR => String::from("R"),
-        KeyCode::KeyS => String::from("S"),
-        KeyCode::KeyT => String::from("T"),
-        KeyCode::KeyU => String::from("U"),
-        KeyCode::KeyV => String::from("V"),
-        KeyCode::KeyW => String::from("W"),
-        KeyCode::KeyX => String::from("X"),
-        KeyCode::KeyY => String::from("Y"),
-        KeyCode::KeyZ => String::from("Z"),
-        _ => panic!()
-    }
-}
\ No newline at end of file
@@ -0,0 +1,201 @@
+use crate::keyboard::{to_upper_glyph, KeyBinding};
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::{ButtonState, InputSystem};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct InternalInputPlugin;
+
+// This plugin turns raw keyboard events and gamepad buttons into the presses the moles react to.
+// Presses are buffered every frame and consumed by the next fixed simulation step,
+// so none are lost or doubled up however the frame rate and fixed rate line up.
+impl Plugin for InternalInputPlugin
+{
+    fn build(&self, app: &mut App)
+    {
+        app.init_resource::<InputMode>()
+            .init_resource::<KeyPresses>()
+            .add_systems(PreUpdate, (collect_key_presses, collect_gamepad_presses).chain().after(InputSystem))
+            .add_systems(FixedPostUpdate, clear_key_presses);
+    }
+}
+
+/// How a key press is matched against the key a mole is bound to.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum InputMode
+{
+    /// Match on the physical key position, ignoring what the OS layout types.
+    #[cfg_attr(not(any(target_os = "android", target_os = "ios")), default)]
+    Physical,
+    /// Match on the character the OS layout produces for the key.
+    Logical,
+    /// Click or tap the moles with a hammer. Phones and tablets start out in this mode,
+    /// since they have no keyboard to speak of.
+    #[cfg_attr(any(target_os = "android", target_os = "ios"), default)]
+    Pointer,
+}
+
+impl InputMode
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            InputMode::Physical => "Physical",
+            InputMode::Logical => "Logical",
+            InputMode::Pointer => "Mouse/Touch",
+        }
+    }
+
+    pub fn cycle(&mut self)
+    {
+        *self = match self
+        {
+            InputMode::Physical => InputMode::Logical,
+            InputMode::Logical => InputMode::Pointer,
+            InputMode::Pointer => InputMode::Physical,
+        };
+    }
+}
+
+/// What a mole is bound to: a key on the keyboard or a button on a gamepad.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputBinding
+{
+    Key(KeyBinding),
+    /// A button on any connected gamepad.
+    Button(GamepadButton),
+}
+
+impl InputBinding
+{
+    /// The keyboard key, for bindings that have one.
+    pub fn key_code(&self) -> Option<KeyCode>
+    {
+        match self
+        {
+            InputBinding::Key(binding) => Some(binding.key_code),
+            InputBinding::Button(_) => None,
+        }
+    }
+}
+
+/// Gamepad buttons moles can be bound to, with the name boards and replays use for them
+/// and the label shown on the mole.
+const GAMEPAD_BUTTONS: [(GamepadButton, &str, &str); 12] = [
+    (GamepadButton::South, "South", "A"),
+    (GamepadButton::East, "East", "B"),
+    (GamepadButton::West, "West", "X"),
+    (GamepadButton::North, "North", "Y"),
+    (GamepadButton::LeftTrigger, "LeftTrigger", "LB"),
+    (GamepadButton::LeftTrigger2, "LeftTrigger2", "LT"),
+    (GamepadButton::RightTrigger, "RightTrigger", "RB"),
+    (GamepadButton::RightTrigger2, "RightTrigger2", "RT"),
+    (GamepadButton::DPadUp, "DPadUp", "^"),
+    (GamepadButton::DPadDown, "DPadDown", "v"),
+    (GamepadButton::DPadLeft, "DPadLeft", "<"),
+    (GamepadButton::DPadRight, "DPadRight", ">"),
+];
+
+/// Look up a gamepad button by its Bevy name (e.g. "South" or "DPadLeft").
+pub fn gamepad_button_from_name(name: &str) -> Option<GamepadButton>
+{
+    return GAMEPAD_BUTTONS.iter().find(|(_, button_name, _)| *button_name == name).map(|(button, _, _)| *button);
+}
+
+pub fn gamepad_button_name(button: GamepadButton) -> Option<&'static str>
+{
+    return GAMEPAD_BUTTONS.iter().find(|(other, _, _)| *other == button).map(|(_, name, _)| *name);
+}
+
+/// Text to show on a mole bound to `button`, using Xbox-style names.
+pub fn gamepad_button_label(button: GamepadButton) -> Option<&'static str>
+{
+    return GAMEPAD_BUTTONS.iter().find(|(other, _, _)| *other == button).map(|(_, _, label)| *label);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyPress
+{
+    /// The key, with whether either shift key was held when it went down, or the gamepad button.
+    pub binding: InputBinding,
+    /// Upper-cased character the key typed, if it typed one.
+    pub glyph: Option<char>,
+}
+
+/// Keys pressed since the last fixed simulation step, in the order they were pressed.
+#[derive(Resource, Default, Debug)]
+pub struct KeyPresses
+{
+    presses: Vec<KeyPress>,
+}
+
+impl KeyPresses
+{
+    pub fn iter(&self) -> impl Iterator<Item = &KeyPress>
+    {
+        return self.presses.iter();
+    }
+
+    pub fn push(&mut self, press: KeyPress)
+    {
+        self.presses.push(press);
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.presses.clear();
+    }
+
+    /// Was the key bound to `binding` / `glyph` pressed since the last step? Gamepad
+    /// buttons type nothing, so they match on the button in every mode, and a clicked
+    /// mole presses exactly the keys it is bound to.
+    pub fn just_pressed(&self, mode: InputMode, binding: InputBinding, glyph: char) -> bool
+    {
+        return self.presses.iter().any(|press| match (mode, binding)
+        {
+            (InputMode::Physical | InputMode::Pointer, _) | (InputMode::Logical, InputBinding::Button(_)) => press.binding == binding,
+            (InputMode::Logical, InputBinding::Key(_)) => press.glyph == Some(glyph),
+        });
+    }
+}
+
+fn collect_key_presses(mut events: EventReader<KeyboardInput>,
+                        keys: Res<ButtonInput<KeyCode>>,
+                        mut presses: ResMut<KeyPresses>)
+{
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    for event in events.read()
+    {
+        if event.state != ButtonState::Pressed || event.repeat
+        {
+            continue;
+        }
+
+        let glyph = match &event.logical_key
+        {
+            Key::Character(chars) => chars.chars().next().map(to_upper_glyph),
+            Key::Space => Some(' '),
+            _ => None,
+        };
+
+        presses.presses.push(KeyPress { binding: InputBinding::Key(KeyBinding { key_code: event.key_code, shift }), glyph });
+    }
+}
+
+fn collect_gamepad_presses(gamepads: Query<&Gamepad>, mut presses: ResMut<KeyPresses>)
+{
+    for gamepad in &gamepads
+    {
+        for button in gamepad.get_just_pressed()
+        {
+            presses.presses.push(KeyPress { binding: InputBinding::Button(*button), glyph: None });
+        }
+    }
+}
+
+fn clear_key_presses(mut presses: ResMut<KeyPresses>)
+{
+    presses.clear();
+}
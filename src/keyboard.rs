@@ -0,0 +1,409 @@
+use bevy::prelude::*;
+
+// =============================================
+// PHYSICAL KEYBOARD
+// =============================================
+
+/// Physical keys of the main block, row by row: number row, top, home and bottom
+/// letter rows, then the space bar. Bevy's `KeyCode`s name the key *position*
+/// using its US QWERTY label, so these never change between layouts.
+const PHYSICAL_ROWS: [&[KeyCode]; 5] =
+[
+    &[KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4, KeyCode::Digit5,
+      KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9, KeyCode::Digit0,
+      KeyCode::Minus, KeyCode::Equal],
+    &[KeyCode::KeyQ, KeyCode::KeyW, KeyCode::KeyE, KeyCode::KeyR, KeyCode::KeyT,
+      KeyCode::KeyY, KeyCode::KeyU, KeyCode::KeyI, KeyCode::KeyO, KeyCode::KeyP,
+      KeyCode::BracketLeft, KeyCode::BracketRight],
+    &[KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD, KeyCode::KeyF, KeyCode::KeyG,
+      KeyCode::KeyH, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL, KeyCode::Semicolon,
+      KeyCode::Quote],
+    &[KeyCode::KeyZ, KeyCode::KeyX, KeyCode::KeyC, KeyCode::KeyV, KeyCode::KeyB,
+      KeyCode::KeyN, KeyCode::KeyM, KeyCode::Comma, KeyCode::Period, KeyCode::Slash],
+    &[KeyCode::Space],
+];
+
+/// Rows a layout assigns glyphs to. The space bar row is the same everywhere.
+const NUM_LAYOUT_ROWS: usize = 4;
+const SPACE_ROW: usize = 4;
+
+// Screen position of the first hole in each row and the gap between holes.
+const ROW_START_X: [f32; 5] = [-550.0, -460.0, -424.0, -358.0, -70.0];
+const ROW_Y: [f32; 5] = [36.0, -38.0, -112.0, -186.0, -260.0];
+const HOLE_SPACING: f32 = 96.0;
+
+/// Where the hole for the key at `row`, `col` of the physical keyboard sits on screen.
+pub fn slot_position(row: usize, col: usize) -> Vec2
+{
+    return Vec2::new(ROW_START_X[row] + col as f32 * HOLE_SPACING, ROW_Y[row]);
+}
+
+/// Find the physical slot (row, column) of `key_code`, if it is part of the main block.
+pub fn slot_of_key(key_code: KeyCode) -> Option<(usize, usize)>
+{
+    for (row_idx, row) in PHYSICAL_ROWS.iter().enumerate()
+    {
+        if let Some(col_idx) = row.iter().position(|k| *k == key_code)
+        {
+            return Some((row_idx, col_idx));
+        }
+    }
+
+    return None;
+}
+
+/// The key in the mirror-image spot on the other half of the letter rows, e.g. P for Q,
+/// or `None` for keys past the tenth column or off the letter rows.
+pub fn mirrored_key(key_code: KeyCode) -> Option<KeyCode>
+{
+    let (row, col) = slot_of_key(key_code)?;
+    if row >= NUM_LAYOUT_ROWS || col > 9
+    {
+        return None;
+    }
+
+    return PHYSICAL_ROWS[row].get(9 - col).copied();
+}
+
+/// Upper-case a glyph, leaving it alone if it has no single-character upper case (e.g. 'ß').
+pub fn to_upper_glyph(glyph: char) -> char
+{
+    let mut upper = glyph.to_uppercase();
+    return match (upper.next(), upper.next())
+    {
+        (Some(c), None) => c,
+        _ => glyph,
+    };
+}
+
+/// A physical key, and whether shift has to be held with it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KeyBinding
+{
+    pub key_code: KeyCode,
+    pub shift: bool,
+}
+
+// =============================================
+// LAYOUT
+// =============================================
+
+/// Describes which glyph each physical key produces, with and without shift.
+#[derive(Clone, Debug)]
+pub struct KeyboardLayout
+{
+    name: String,
+    rows: [Vec<char>; NUM_LAYOUT_ROWS],
+    shifted_rows: [Vec<char>; NUM_LAYOUT_ROWS],
+}
+
+impl KeyboardLayout
+{
+    /// Create a layout from the glyphs printed on the number, top, home and bottom rows,
+    /// left to right, both unshifted and shifted. Rows may be shorter than the physical
+    /// row but not longer.
+    pub fn from_rows(name: &str, rows: [&str; NUM_LAYOUT_ROWS], shifted_rows: [&str; NUM_LAYOUT_ROWS]) -> Result<Self, String>
+    {
+        let to_glyphs = |row: &str| row.chars().map(to_upper_glyph).collect::<Vec<char>>();
+        let rows = rows.map(to_glyphs);
+        let shifted_rows = shifted_rows.map(to_glyphs);
+
+        for (idx, (row, shifted_row)) in rows.iter().zip(shifted_rows.iter()).enumerate()
+        {
+            let max_len = PHYSICAL_ROWS[idx].len();
+            if row.len() > max_len || shifted_row.len() > max_len
+            {
+                return Err(format!("Layout {} has too many keys on row {}, max is {}.", name, idx, max_len));
+            }
+        }
+
+        return Ok(Self { name: String::from(name), rows, shifted_rows });
+    }
+
+    pub fn qwerty() -> Self
+    {
+        return Self::from_rows("QWERTY",
+                                ["1234567890-=", "QWERTYUIOP[]", "ASDFGHJKL;'", "ZXCVBNM,./"],
+                                ["!@#$%^&*()_+", "QWERTYUIOP{}", "ASDFGHJKL:\"", "ZXCVBNM<>?"]).unwrap();
+    }
+
+    pub fn azerty() -> Self
+    {
+        return Self::from_rows("AZERTY",
+                                ["&é\"'(-è_çà)=", "AZERTYUIOP^$", "QSDFGHJKLMù", "WXCVBN,;:!"],
+                                ["1234567890°+", "AZERTYUIOP¨£", "QSDFGHJKLM%", "WXCVBN?./§"]).unwrap();
+    }
+
+    pub fn qwertz() -> Self
+    {
+        return Self::from_rows("QWERTZ",
+                                ["1234567890ß´", "QWERTZUIOPÜ+", "ASDFGHJKLÖÄ", "YXCVBNM,.-"],
+                                ["!\"§$%&/()=?`", "QWERTZUIOPÜ*", "ASDFGHJKLÖÄ", "YXCVBNM;:_"]).unwrap();
+    }
+
+    pub fn dvorak() -> Self
+    {
+        return Self::from_rows("Dvorak",
+                                ["1234567890[]", "',.PYFGCRL/=", "AOEUIDHTNS-", ";QJKXBMWVZ"],
+                                ["!@#$%^&*(){}", "\"<>PYFGCRL?+", "AOEUIDHTNS_", ":QJKXBMWVZ"]).unwrap();
+    }
+
+    pub fn colemak() -> Self
+    {
+        return Self::from_rows("Colemak",
+                                ["1234567890-=", "QWFPGJLUY;[]", "ARSTDHNEIO'", "ZXCVBKM,./"],
+                                ["!@#$%^&*()_+", "QWFPGJLUY:{}", "ARSTDHNEIO\"", "ZXCVBKM<>?"]).unwrap();
+    }
+
+    pub fn name(&self) -> &str
+    {
+        return &self.name;
+    }
+
+    /// Find the physical slot (row, column) that produces `glyph`, and whether shift is needed.
+    fn find_glyph(&self, glyph: char) -> Option<(usize, usize, bool)>
+    {
+        let glyph = to_upper_glyph(glyph);
+        if glyph == ' '
+        {
+            return Some((SPACE_ROW, 0, false));
+        }
+
+        // Prefer the unshifted key, so letters never ask for shift.
+        for (shift, rows) in [(false, &self.rows), (true, &self.shifted_rows)]
+        {
+            for (row_idx, row) in rows.iter().enumerate()
+            {
+                if let Some(col_idx) = row.iter().position(|c| *c == glyph)
+                {
+                    return Some((row_idx, col_idx, shift));
+                }
+            }
+        }
+
+        return None;
+    }
+
+    /// Find the physical slot (row, column) that produces `glyph`.
+    pub fn slot_of(&self, glyph: char) -> Option<(usize, usize)>
+    {
+        let (row, col, _) = self.find_glyph(glyph)?;
+        return Some((row, col));
+    }
+
+    /// Physical key (and shift state) that has to be pressed to type `glyph` on this layout.
+    pub fn binding_for_glyph(&self, glyph: char) -> Option<KeyBinding>
+    {
+        let (row, col, shift) = self.find_glyph(glyph)?;
+        return Some(KeyBinding { key_code: PHYSICAL_ROWS[row][col], shift });
+    }
+
+    /// Glyph this layout prints on the physical key `key_code`, with or without shift.
+    pub fn key_code_to_string(&self, key_code: KeyCode, shift: bool) -> String
+    {
+        if let Some((row, col)) = slot_of_key(key_code)
+        {
+            let rows = if shift { &self.shifted_rows } else { &self.rows };
+            if let Some(glyph) = rows.get(row).and_then(|row| row.get(col))
+            {
+                return glyph.to_string();
+            }
+        }
+
+        // Keys outside the layout print the same on every layout.
+        let fixed = match key_code
+        {
+            KeyCode::Space => "Spc",
+            KeyCode::Numpad0 => "0",
+            KeyCode::Numpad1 => "1",
+            KeyCode::Numpad2 => "2",
+            KeyCode::Numpad3 => "3",
+            KeyCode::Numpad4 => "4",
+            KeyCode::Numpad5 => "5",
+            KeyCode::Numpad6 => "6",
+            KeyCode::Numpad7 => "7",
+            KeyCode::Numpad8 => "8",
+            KeyCode::Numpad9 => "9",
+            KeyCode::NumpadAdd => "+",
+            KeyCode::NumpadSubtract => "-",
+            KeyCode::NumpadMultiply => "*",
+            KeyCode::NumpadDivide => "/",
+            KeyCode::NumpadDecimal => ".",
+            _ => "?",
+        };
+
+        return String::from(fixed);
+    }
+}
+
+/// All layouts the player can pick from, plus the one currently in use.
+/// Insert this resource before adding the `GamePlugin` to supply extra layouts.
+#[derive(Resource, Clone, Debug)]
+pub struct KeyboardLayouts
+{
+    layouts: Vec<KeyboardLayout>,
+    curr_idx: usize,
+}
+
+impl KeyboardLayouts
+{
+    pub fn new() -> Self
+    {
+        Self
+        {
+            layouts: vec![KeyboardLayout::qwerty(),
+                          KeyboardLayout::azerty(),
+                          KeyboardLayout::qwertz(),
+                          KeyboardLayout::dvorak(),
+                          KeyboardLayout::colemak()],
+            curr_idx: 0,
+        }
+    }
+
+    pub fn add(&mut self, layout: KeyboardLayout)
+    {
+        self.layouts.push(layout);
+    }
+
+    pub fn current(&self) -> &KeyboardLayout
+    {
+        return &self.layouts[self.curr_idx];
+    }
+
+    pub fn cycle(&mut self)
+    {
+        self.curr_idx = (self.curr_idx + 1) % self.layouts.len();
+    }
+
+    /// Switch to the layout called `name`, if there is one.
+    pub fn select(&mut self, name: &str)
+    {
+        if let Some(idx) = self.layouts.iter().position(|layout| layout.name() == name)
+        {
+            self.curr_idx = idx;
+        }
+    }
+}
+
+impl Default for KeyboardLayouts
+{
+    fn default() -> Self
+    {
+        return KeyboardLayouts::new();
+    }
+}
+
+/// Look up a physical key by its Bevy `KeyCode` name, as used in board files.
+pub fn key_code_from_name(name: &str) -> Option<KeyCode>
+{
+    if let Some(key) = PHYSICAL_ROWS.iter().copied().flatten().find(|k| format!("{:?}", k) == name)
+    {
+        return Some(*key);
+    }
+
+    let key = match name
+    {
+        "Numpad0" => KeyCode::Numpad0,
+        "Numpad1" => KeyCode::Numpad1,
+        "Numpad2" => KeyCode::Numpad2,
+        "Numpad3" => KeyCode::Numpad3,
+        "Numpad4" => KeyCode::Numpad4,
+        "Numpad5" => KeyCode::Numpad5,
+        "Numpad6" => KeyCode::Numpad6,
+        "Numpad7" => KeyCode::Numpad7,
+        "Numpad8" => KeyCode::Numpad8,
+        "Numpad9" => KeyCode::Numpad9,
+        "NumpadAdd" => KeyCode::NumpadAdd,
+        "NumpadSubtract" => KeyCode::NumpadSubtract,
+        "NumpadMultiply" => KeyCode::NumpadMultiply,
+        "NumpadDivide" => KeyCode::NumpadDivide,
+        "NumpadDecimal" => KeyCode::NumpadDecimal,
+        _ => return None,
+    };
+
+    return Some(key);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn slots_follow_the_physical_rows()
+    {
+        assert_eq!(slot_of_key(KeyCode::Digit1), Some((0, 0)));
+        assert_eq!(slot_of_key(KeyCode::KeyP), Some((1, 9)));
+        assert_eq!(slot_of_key(KeyCode::Quote), Some((2, 10)));
+        assert_eq!(slot_of_key(KeyCode::Space), Some((SPACE_ROW, 0)));
+        assert_eq!(slot_of_key(KeyCode::Numpad5), None);
+    }
+
+    #[test]
+    fn mirrored_keys_pair_up_across_the_halves()
+    {
+        assert_eq!(mirrored_key(KeyCode::KeyQ), Some(KeyCode::KeyP));
+        assert_eq!(mirrored_key(KeyCode::KeyP), Some(KeyCode::KeyQ));
+        assert_eq!(mirrored_key(KeyCode::KeyF), Some(KeyCode::KeyJ));
+        assert_eq!(mirrored_key(KeyCode::KeyZ), Some(KeyCode::Slash));
+        assert_eq!(mirrored_key(KeyCode::Digit1), Some(KeyCode::Digit0));
+    }
+
+    #[test]
+    fn keys_without_a_partner_do_not_mirror()
+    {
+        assert_eq!(mirrored_key(KeyCode::BracketLeft), None);
+        assert_eq!(mirrored_key(KeyCode::Quote), None);
+        assert_eq!(mirrored_key(KeyCode::Space), None);
+        assert_eq!(mirrored_key(KeyCode::Numpad1), None);
+    }
+
+    #[test]
+    fn glyphs_map_to_the_key_that_types_them()
+    {
+        let key = |key_code, shift| Some(KeyBinding { key_code, shift });
+
+        assert_eq!(KeyboardLayout::qwerty().binding_for_glyph('a'), key(KeyCode::KeyA, false));
+        assert_eq!(KeyboardLayout::azerty().binding_for_glyph('A'), key(KeyCode::KeyQ, false));
+        assert_eq!(KeyboardLayout::qwertz().binding_for_glyph('Z'), key(KeyCode::KeyY, false));
+        assert_eq!(KeyboardLayout::dvorak().binding_for_glyph('S'), key(KeyCode::Semicolon, false));
+        assert_eq!(KeyboardLayout::colemak().binding_for_glyph('E'), key(KeyCode::KeyK, false));
+        assert_eq!(KeyboardLayout::qwerty().binding_for_glyph(' '), key(KeyCode::Space, false));
+    }
+
+    #[test]
+    fn shifted_glyphs_ask_for_shift()
+    {
+        let key = |key_code, shift| Some(KeyBinding { key_code, shift });
+
+        assert_eq!(KeyboardLayout::qwerty().binding_for_glyph('!'), key(KeyCode::Digit1, true));
+        // AZERTY types digits with shift.
+        assert_eq!(KeyboardLayout::azerty().binding_for_glyph('1'), key(KeyCode::Digit1, true));
+        assert_eq!(KeyboardLayout::qwerty().binding_for_glyph('é'), None);
+    }
+
+    #[test]
+    fn keys_print_their_layout_glyph()
+    {
+        let azerty = KeyboardLayout::azerty();
+        assert_eq!(azerty.key_code_to_string(KeyCode::KeyQ, false), "A");
+        assert_eq!(azerty.key_code_to_string(KeyCode::Digit2, true), "2");
+        assert_eq!(azerty.key_code_to_string(KeyCode::Numpad7, false), "7");
+        assert_eq!(azerty.key_code_to_string(KeyCode::Space, false), "Spc");
+    }
+
+    #[test]
+    fn layouts_reject_rows_that_are_too_long()
+    {
+        let rows = ["1234567890-=+", "", "", ""];
+        assert!(KeyboardLayout::from_rows("Long", rows, ["", "", "", ""]).is_err());
+    }
+
+    #[test]
+    fn upper_case_leaves_glyphs_without_a_single_upper_case()
+    {
+        assert_eq!(to_upper_glyph('q'), 'Q');
+        assert_eq!(to_upper_glyph('ß'), 'ß');
+        assert_eq!(to_upper_glyph(';'), ';');
+    }
+}
@@ -0,0 +1,152 @@
+use crate::storage;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const LEADERBOARD_KEY: &str = "leaderboard.ron";
+pub const MAX_ENTRIES: usize = 10;
+pub const MAX_NAME_LEN: usize = 12;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScoreEntry
+{
+    pub name: String,
+    pub score: i32,
+    pub seed: u64,
+}
+
+/// Local high scores, one top-10 table per game mode and board.
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct Leaderboard
+{
+    tables: HashMap<String, Vec<ScoreEntry>>,
+    /// Name entered most recently, offered again for the next high score.
+    pub last_name: String,
+}
+
+impl Leaderboard
+{
+    pub fn load() -> Self
+    {
+        return storage::load(LEADERBOARD_KEY)
+                    .and_then(|contents| ron::from_str(&contents).ok())
+                    .unwrap_or_default();
+    }
+
+    pub fn save(&self)
+    {
+        let result = ron::to_string(self).map_err(|err| err.to_string())
+                        .and_then(|contents| storage::save(LEADERBOARD_KEY, &contents));
+
+        if let Err(err) = result
+        {
+            warn!("Could not save leaderboard: {}", err);
+        }
+    }
+
+    fn table_key(mode: &str, board: &str) -> String
+    {
+        return format!("{}/{}", mode, board);
+    }
+
+    /// Best scores for `mode` on `board`, highest first.
+    pub fn top(&self, mode: &str, board: &str) -> &[ScoreEntry]
+    {
+        return self.tables.get(&Self::table_key(mode, board)).map(Vec::as_slice).unwrap_or(&[]);
+    }
+
+    /// Would `score` make it onto the table for `mode` on `board`?
+    pub fn qualifies(&self, mode: &str, board: &str, score: i32) -> bool
+    {
+        let table = self.top(mode, board);
+        return score > 0 && (table.len() < MAX_ENTRIES || table.last().is_some_and(|entry| score > entry.score));
+    }
+
+    pub fn submit(&mut self, mode: &str, board: &str, entry: ScoreEntry)
+    {
+        self.insert(mode, board, entry);
+        self.save();
+    }
+
+    /// Put `entry` in its place on the table, below any equal scores, and drop whoever falls off the end.
+    fn insert(&mut self, mode: &str, board: &str, entry: ScoreEntry)
+    {
+        self.last_name = entry.name.clone();
+
+        let table = self.tables.entry(Self::table_key(mode, board)).or_default();
+        let idx = table.iter().position(|other| entry.score > other.score).unwrap_or(table.len());
+        table.insert(idx, entry);
+        table.truncate(MAX_ENTRIES);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn entry(name: &str, score: i32) -> ScoreEntry
+    {
+        return ScoreEntry { name: String::from(name), score, seed: 0 };
+    }
+
+    fn names(leaderboard: &Leaderboard, mode: &str, board: &str) -> Vec<String>
+    {
+        return leaderboard.top(mode, board).iter().map(|entry| entry.name.clone()).collect();
+    }
+
+    #[test]
+    fn entries_are_kept_highest_first()
+    {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.insert("Classic", "Classic", entry("B", 5));
+        leaderboard.insert("Classic", "Classic", entry("A", 9));
+        leaderboard.insert("Classic", "Classic", entry("C", 5));
+
+        // Ties go below the score that was there first.
+        assert_eq!(names(&leaderboard, "Classic", "Classic"), ["A", "B", "C"]);
+        assert_eq!(leaderboard.last_name, "C");
+    }
+
+    #[test]
+    fn tables_are_truncated()
+    {
+        let mut leaderboard = Leaderboard::default();
+        for score in 1..=MAX_ENTRIES as i32 + 3
+        {
+            leaderboard.insert("Classic", "Classic", entry("A", score));
+        }
+
+        let table = leaderboard.top("Classic", "Classic");
+        assert_eq!(table.len(), MAX_ENTRIES);
+        assert_eq!(table.first().map(|entry| entry.score), Some(MAX_ENTRIES as i32 + 3));
+        assert_eq!(table.last().map(|entry| entry.score), Some(4));
+    }
+
+    #[test]
+    fn only_scores_that_make_the_table_qualify()
+    {
+        let mut leaderboard = Leaderboard::default();
+        assert!(!leaderboard.qualifies("Classic", "Classic", 0));
+        assert!(leaderboard.qualifies("Classic", "Classic", 1));
+
+        for _ in 0..MAX_ENTRIES
+        {
+            leaderboard.insert("Classic", "Classic", entry("A", 10));
+        }
+        assert!(!leaderboard.qualifies("Classic", "Classic", 10));
+        assert!(leaderboard.qualifies("Classic", "Classic", 11));
+    }
+
+    #[test]
+    fn each_mode_and_board_has_its_own_table()
+    {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.insert("Classic", "Classic", entry("A", 5));
+        leaderboard.insert("Blitz", "Classic", entry("B", 5));
+
+        assert_eq!(names(&leaderboard, "Classic", "Classic"), ["A"]);
+        assert_eq!(names(&leaderboard, "Blitz", "Classic"), ["B"]);
+        assert!(leaderboard.top("Classic", "Numpad").is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+use crate::keyboard::slot_of_key;
+use crate::storage;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const LEVEL_PROGRESS_KEY: &str = "levels.ron";
+
+/// Seconds between one step of a boss wave and the next.
+const BOSS_WAVE_STEP: f32 = 0.4;
+
+// =============================================
+// LEVELS
+// =============================================
+
+/// How the moles come up in the boss wave that ends a level.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BossPattern
+{
+    /// One mole after another along a keyboard row (0 is the number row), left to right.
+    RowSweep(usize),
+    /// Every row at once, so a column of moles sweeps left to right across the board.
+    ColumnSweep,
+    /// Every mole at once.
+    Burst,
+}
+
+impl BossPattern
+{
+    /// Seconds after the wave starts that each hole's mole comes up, given the key of each hole.
+    /// Holes off the main block only join a `Burst`, and a row the board has no holes on falls back to one.
+    fn schedule(&self, holes: &[(usize, KeyCode)]) -> Vec<(usize, f32)>
+    {
+        let slots = holes.iter().filter_map(|(hole_idx, key_code)| slot_of_key(*key_code).map(|slot| (*hole_idx, slot)));
+
+        let mut schedule: Vec<(usize, f32)> = match self
+        {
+            BossPattern::RowSweep(row) =>
+            {
+                let mut in_row: Vec<(usize, usize)> = slots.filter(|(_, (slot_row, _))| slot_row == row)
+                                                            .map(|(hole_idx, (_, col))| (hole_idx, col))
+                                                            .collect();
+                in_row.sort_by_key(|(_, col)| *col);
+                in_row.iter().enumerate().map(|(step, (hole_idx, _))| (*hole_idx, step as f32 * BOSS_WAVE_STEP)).collect()
+            }
+            BossPattern::ColumnSweep => slots.map(|(hole_idx, (_, col))| (hole_idx, col as f32 * BOSS_WAVE_STEP)).collect(),
+            BossPattern::Burst => Vec::new(),
+        };
+
+        if schedule.is_empty()
+        {
+            schedule = holes.iter().map(|(hole_idx, _)| (*hole_idx, 0.0)).collect();
+        }
+
+        return schedule;
+    }
+}
+
+/// One stage of Levels mode. The boss wave starts once `target_score` is reached,
+/// and the level is complete when it is over.
+pub struct Level
+{
+    pub name: &'static str,
+    /// Name of the board the level is played on.
+    pub board: &'static str,
+    /// Name of the difficulty curve the level is played on.
+    pub difficulty: &'static str,
+    pub target_score: i32,
+    /// Whether golden, bomb, armored and decoy moles and power-ups turn up.
+    pub special_moles: bool,
+    pub boss: BossPattern,
+}
+
+pub const LEVELS: [Level; 5] = [
+    Level { name: "Warm-up", board: "Home Row", difficulty: "Easy", target_score: 15, special_moles: false, boss: BossPattern::RowSweep(2) },
+    Level { name: "Reach Up", board: "Standard", difficulty: "Normal", target_score: 30, special_moles: false, boss: BossPattern::RowSweep(1) },
+    Level { name: "Odd Moles", board: "Standard", difficulty: "Normal", target_score: 45, special_moles: true, boss: BossPattern::ColumnSweep },
+    Level { name: "Numbers", board: "Full Keyboard", difficulty: "Hard", target_score: 60, special_moles: true, boss: BossPattern::RowSweep(0) },
+    Level { name: "Mayhem", board: "Standard", difficulty: "Insane", target_score: 80, special_moles: true, boss: BossPattern::Burst },
+];
+
+// =============================================
+// BOSS WAVE
+// =============================================
+
+/// A boss wave under way: moles still to come up, and when.
+#[derive(Clone, Debug)]
+pub struct BossWave
+{
+    /// (hole index, seconds into the wave), soonest first.
+    schedule: Vec<(usize, f32)>,
+    elapsed: f32,
+}
+
+impl BossWave
+{
+    /// Start `pattern` on the board whose holes have the given keys.
+    pub fn new(pattern: BossPattern, holes: &[(usize, KeyCode)]) -> Self
+    {
+        let mut schedule = pattern.schedule(holes);
+        schedule.sort_by(|(a_idx, a_time), (b_idx, b_time)| a_time.total_cmp(b_time).then(a_idx.cmp(b_idx)));
+
+        return Self { schedule, elapsed: 0.0 };
+    }
+
+    /// Advance the wave by `delta_secs`, returning the holes whose moles should come up now.
+    pub fn tick(&mut self, delta_secs: f32) -> Vec<usize>
+    {
+        self.elapsed += delta_secs;
+
+        let num_due = self.schedule.iter().take_while(|(_, time)| *time <= self.elapsed).count();
+        return self.schedule.drain(..num_due).map(|(hole_idx, _)| hole_idx).collect();
+    }
+
+    /// Every mole of the wave has come up.
+    pub fn is_done(&self) -> bool
+    {
+        return self.schedule.is_empty();
+    }
+}
+
+// =============================================
+// PROGRESS
+// =============================================
+
+/// How far through the levels the player has got, saved between sessions.
+#[derive(Resource, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct LevelProgress
+{
+    /// Number of levels that can be picked, counting from the first.
+    unlocked: usize,
+}
+
+impl Default for LevelProgress
+{
+    fn default() -> Self
+    {
+        Self { unlocked: 1 }
+    }
+}
+
+impl LevelProgress
+{
+    pub fn load() -> Self
+    {
+        return storage::load(LEVEL_PROGRESS_KEY)
+                    .and_then(|contents| ron::from_str(&contents).ok())
+                    .unwrap_or_default();
+    }
+
+    pub fn save(&self)
+    {
+        let result = ron::to_string(self).map_err(|err| err.to_string())
+                        .and_then(|contents| storage::save(LEVEL_PROGRESS_KEY, &contents));
+
+        if let Err(err) = result
+        {
+            warn!("Could not save level progress: {}", err);
+        }
+    }
+
+    pub fn is_unlocked(&self, level: usize) -> bool
+    {
+        return level < self.num_unlocked();
+    }
+
+    pub fn num_unlocked(&self) -> usize
+    {
+        return self.unlocked.clamp(1, LEVELS.len());
+    }
+
+    /// Level `level` was completed, so the one after it can be played.
+    pub fn complete(&mut self, level: usize)
+    {
+        if self.unlock_after(level)
+        {
+            self.save();
+        }
+    }
+
+    /// Unlock the level after `level`, returning whether anything new was unlocked.
+    fn unlock_after(&mut self, level: usize) -> bool
+    {
+        let unlocked = (level + 2).min(LEVELS.len());
+        if unlocked <= self.unlocked
+        {
+            return false;
+        }
+
+        self.unlocked = unlocked;
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn row_sweep_goes_left_to_right_along_its_row()
+    {
+        let holes = [(0, KeyCode::KeyD), (1, KeyCode::KeyQ), (2, KeyCode::KeyA), (3, KeyCode::KeyS)];
+        let mut wave = BossWave::new(BossPattern::RowSweep(2), &holes);
+
+        assert_eq!(wave.tick(0.0), vec![2]);
+        assert_eq!(wave.tick(BOSS_WAVE_STEP), vec![3]);
+        assert_eq!(wave.tick(BOSS_WAVE_STEP), vec![0]);
+        assert!(wave.is_done());
+    }
+
+    #[test]
+    fn empty_row_falls_back_to_burst()
+    {
+        let holes = [(0, KeyCode::KeyQ), (1, KeyCode::KeyW), (2, KeyCode::Numpad1)];
+        let mut wave = BossWave::new(BossPattern::RowSweep(0), &holes);
+
+        assert_eq!(wave.tick(0.0), vec![0, 1, 2]);
+        assert!(wave.is_done());
+    }
+
+    #[test]
+    fn tick_only_drains_due_holes()
+    {
+        let holes = [(0, KeyCode::KeyA), (1, KeyCode::KeyS), (2, KeyCode::KeyD)];
+        let mut wave = BossWave::new(BossPattern::ColumnSweep, &holes);
+
+        assert_eq!(wave.tick(BOSS_WAVE_STEP * 0.5), vec![0]);
+        assert_eq!(wave.tick(BOSS_WAVE_STEP * 0.25), Vec::<usize>::new());
+        assert!(!wave.is_done());
+        assert_eq!(wave.tick(BOSS_WAVE_STEP * 2.0), vec![1, 2]);
+        assert!(wave.is_done());
+    }
+
+    #[test]
+    fn unlocks_stop_at_the_last_level()
+    {
+        let mut progress = LevelProgress::default();
+        assert!(progress.is_unlocked(0));
+        assert!(!progress.is_unlocked(1));
+
+        assert!(progress.unlock_after(0));
+        assert!(progress.is_unlocked(1));
+        assert!(!progress.unlock_after(0));
+
+        assert!(progress.unlock_after(LEVELS.len() - 2));
+        assert!(!progress.unlock_after(LEVELS.len() - 1));
+        assert_eq!(progress.num_unlocked(), LEVELS.len());
+    }
+}
@@ -1,12 +1,28 @@
 #![allow(clippy::type_complexity)]
 
 mod audio;
+mod board;
+mod difficulty;
+mod game_mode;
+mod input;
 mod loading;
 mod in_game;
+mod keyboard;
+mod leaderboard;
+mod levels;
+pub mod online;
+mod replay;
+mod rng;
+mod scoring;
+mod settings;
+mod storage;
+mod words;
 
 use crate::audio::InternalAudioPlugin;
+use crate::input::InternalInputPlugin;
 use crate::loading::LoadingPlugin;
 use crate::in_game::InGamePlugin;
+pub use crate::keyboard::{KeyboardLayout, KeyboardLayouts};
 
 use bevy::app::App;
 #[cfg(debug_assertions)]
@@ -33,6 +49,7 @@ impl Plugin for GamePlugin
     {
         app.init_state::<GameState>().add_plugins((LoadingPlugin,
                                                     InGamePlugin,
+                                                    InternalInputPlugin,
                                                     InternalAudioPlugin));
 
         #[cfg(debug_assertions)]
@@ -1,3 +1,5 @@
+use crate::board::{BoardDefinition, BoardLoader};
+use crate::difficulty::{CurveLoader, PiecewiseCurve};
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
@@ -12,10 +14,16 @@ impl Plugin for LoadingPlugin
 {
     fn build(&self, app: &mut App)
     {
-        app.add_loading_state(
+        app.init_asset::<BoardDefinition>()
+            .init_asset_loader::<BoardLoader>()
+            .init_asset::<PiecewiseCurve>()
+            .init_asset_loader::<CurveLoader>()
+            .add_loading_state(
             LoadingState::new(GameState::Loading)
                 .continue_to_state(GameState::Playing)
                 .load_collection::<AudioAssets>()
+                .load_collection::<BoardAssets>()
+                .load_collection::<DifficultyAssets>()
                 .load_collection::<TextureAssets>()
                 .load_collection::<FontAssets>(),
         );
@@ -44,6 +52,12 @@ pub struct TextureAssets
     #[asset(path = "textures/game_bg.png")]
     pub game_bg: Handle<Image>,
 
+    #[asset(path = "textures/hammer.png")]
+    pub hammer: Handle<Image>,
+
+    #[asset(path = "textures/hammer_cursor.png")]
+    pub hammer_cursor: Handle<Image>,
+
     #[asset(path = "textures/hearts.png")]
     pub hearts: Handle<Image>,
 
@@ -54,6 +68,26 @@ pub struct TextureAssets
     pub mole: Handle<Image>,
 }
 
+#[derive(AssetCollection, Resource)]
+pub struct BoardAssets
+{
+    #[asset(paths("boards/standard.board.ron",
+                  "boards/home_row.board.ron",
+                  "boards/numpad.board.ron",
+                  "boards/full_keyboard.board.ron",
+                  "boards/chords.board.ron",
+                  "boards/versus.board.ron",
+                  "boards/gamepad.board.ron"), collection(typed))]
+    pub boards: Vec<Handle<BoardDefinition>>,
+}
+
+/// Difficulty curves defined in data files, offered after the built-in presets.
+#[derive(AssetCollection, Resource)]
+pub struct DifficultyAssets
+{
+    #[asset(paths("difficulty/marathon.curve.ron"), collection(typed))]
+    pub curves: Vec<Handle<PiecewiseCurve>>,
+}
 
 #[derive(AssetCollection, Resource)]
 pub struct FontAssets
@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+
+/// Port the relay listens on unless told otherwise.
+pub const DEFAULT_RELAY_PORT: u16 = 7878;
+/// Largest message either side sends, in bytes.
+pub const MAX_DATAGRAM: usize = 4096;
+/// Seconds between messages a client sends even when nothing changed, so the relay knows it is still there.
+const KEEPALIVE_SECS: f32 = 1.0;
+
+// =============================================
+// PROTOCOL
+// =============================================
+
+/// Sent by a client to the relay.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum ClientMessage
+{
+    /// Join the lobby, or let the relay know we are still in it.
+    Join { name: String },
+    /// Start a race for everyone in the lobby, on this board and difficulty.
+    Start { board: String, difficulty: String },
+    /// Score so far in the current race.
+    Score { score: i32, finished: bool },
+    Leave,
+}
+
+/// Sent by the relay to every client in the lobby.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum RelayMessage
+{
+    /// Names of the players in the lobby.
+    Lobby { players: Vec<String> },
+    /// Sent to a client that joined, with the id its race entries will carry.
+    Welcome { id: u32 },
+    /// Start a race from `seed`. Everyone gets the same seed, so the moles start out the same for all.
+    Start { seed: u64, board: String, difficulty: String },
+    /// Live scores in the current race, highest first.
+    Standings { entries: Vec<RaceEntry> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RaceEntry
+{
+    /// Given out by the relay, since names needn't be unique.
+    pub id: u32,
+    pub name: String,
+    pub score: i32,
+    pub finished: bool,
+}
+
+pub fn encode<T: Serialize>(message: &T) -> Option<Vec<u8>>
+{
+    return ron::to_string(message).ok().map(String::into_bytes);
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Option<T>
+{
+    return ron::de::from_bytes(bytes).ok();
+}
+
+// =============================================
+// CLIENT
+// =============================================
+
+/// Connection to a race relay.
+#[derive(Resource, Default)]
+pub struct RaceClient
+{
+    socket: Option<UdpSocket>,
+    /// Names of the players in the lobby, as last heard from the relay.
+    pub lobby: Vec<String>,
+    /// Scores in the current or last race.
+    pub standings: Vec<RaceEntry>,
+    /// Why the last connection attempt failed.
+    pub error: Option<String>,
+    /// Our id in the standings, once the relay has sent it.
+    pub id: Option<u32>,
+    /// Last score sent, so unchanged scores aren't sent every frame.
+    last_score: Option<(i32, bool)>,
+    since_sent: f32,
+}
+
+impl RaceClient
+{
+    pub fn is_connected(&self) -> bool
+    {
+        return self.socket.is_some();
+    }
+
+    /// Open a socket to the relay at `address` and join its lobby as `name`.
+    pub fn connect(&mut self, address: &str, name: &str)
+    {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+                        .and_then(|socket| socket.connect(address).map(|_| socket))
+                        .and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
+
+        match socket
+        {
+            Ok(socket) =>
+            {
+                *self = Self { socket: Some(socket), ..default() };
+                self.send(&ClientMessage::Join { name: String::from(name) });
+            }
+            Err(err) =>
+            {
+                warn!("Could not reach relay at {}: {}", address, err);
+                self.error = Some(err.to_string());
+            }
+        }
+    }
+
+    pub fn disconnect(&mut self)
+    {
+        self.send(&ClientMessage::Leave);
+        *self = Self::default();
+    }
+
+    pub fn send(&mut self, message: &ClientMessage)
+    {
+        let Some(socket) = &self.socket else
+        {
+            return;
+        };
+
+        if let Some(bytes) = encode(message)
+        {
+            if let Err(err) = socket.send(&bytes)
+            {
+                // The relay not listening (yet) shows up here as connection refused, which the next keepalive retries.
+                if err.kind() != ErrorKind::ConnectionRefused
+                {
+                    warn!("Could not send to relay: {}", err);
+                }
+            }
+        }
+        self.since_sent = 0.0;
+    }
+
+    /// Send a keepalive if nothing else was sent for a while.
+    pub fn keep_alive(&mut self, name: &str, delta_secs: f32)
+    {
+        self.since_sent += delta_secs;
+        if self.since_sent >= KEEPALIVE_SECS
+        {
+            self.send(&ClientMessage::Join { name: String::from(name) });
+        }
+    }
+
+    /// Send our score if it changed, or as a keepalive.
+    pub fn report_score(&mut self, score: i32, finished: bool, delta_secs: f32)
+    {
+        self.since_sent += delta_secs;
+        if self.last_score != Some((score, finished)) || self.since_sent >= KEEPALIVE_SECS
+        {
+            self.last_score = Some((score, finished));
+            self.send(&ClientMessage::Score { score, finished });
+        }
+    }
+
+    /// Read everything the relay sent since last time. Lobby and standings updates are
+    /// kept here, and the start of a race is returned as (seed, board, difficulty).
+    pub fn receive(&mut self) -> Option<(u64, String, String)>
+    {
+        let mut start = None;
+        let mut buf = [0u8; MAX_DATAGRAM];
+        while let Some(socket) = &self.socket
+        {
+            let len = match socket.recv(&mut buf)
+            {
+                Ok(len) => len,
+                Err(err) if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::ConnectionRefused => break,
+                Err(err) =>
+                {
+                    warn!("Could not receive from relay: {}", err);
+                    break;
+                }
+            };
+
+            match decode::<RelayMessage>(&buf[..len])
+            {
+                Some(RelayMessage::Lobby { players }) => self.lobby = players,
+                Some(RelayMessage::Welcome { id }) => self.id = Some(id),
+                Some(RelayMessage::Standings { entries }) => self.standings = entries,
+                Some(RelayMessage::Start { seed, board, difficulty }) =>
+                {
+                    self.standings.clear();
+                    self.last_score = None;
+                    start = Some((seed, board, difficulty));
+                }
+                None => warn!("Ignoring a message from the relay that could not be read"),
+            }
+        }
+
+        return start;
+    }
+
+    /// Our 1-based place in the standings.
+    pub fn place(&self) -> Option<usize>
+    {
+        let id = self.id?;
+        return self.standings.iter().position(|entry| entry.id == id).map(|idx| idx + 1);
+    }
+}
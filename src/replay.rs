@@ -0,0 +1,262 @@
+use crate::game_mode::{GameMode, KeyDrill};
+use crate::input::{gamepad_button_from_name, gamepad_button_name, InputBinding, InputMode, KeyPress};
+use crate::keyboard::{key_code_from_name, KeyBinding};
+use crate::settings::MAX_HEARTS;
+use crate::storage;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const LAST_REPLAY_KEY: &str = "last_replay.ron";
+
+// =============================================
+// REPLAY
+// =============================================
+
+/// Everything needed to play a round back exactly: the seed and setup it started
+/// from, and every key pressed during it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Replay
+{
+    pub seed: u64,
+    #[serde(default)]
+    pub mode: GameMode,
+    /// Keys drilled, only used in Zen mode.
+    #[serde(default)]
+    pub drill: KeyDrill,
+    pub board: String,
+    pub layout: String,
+    pub input_mode: InputMode,
+    /// Name of the difficulty curve. Replays from before curves existed played on Normal.
+    #[serde(default = "default_difficulty")]
+    pub difficulty: String,
+    /// Hit rate the adaptive difficulty was aiming for, if it was on.
+    #[serde(default)]
+    pub adaptive_target: Option<f32>,
+    /// Index into `LEVELS` of the level played, in Levels mode.
+    #[serde(default)]
+    pub level: Option<usize>,
+    /// Hearts the round started with. Replays from before the setting existed had them all.
+    #[serde(default = "default_starting_hearts")]
+    pub starting_hearts: i32,
+    pub presses: Vec<ReplayPress>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayPress
+{
+    /// Seconds since the round started.
+    pub time: f32,
+    /// Name of the physical key, as accepted by `key_code_from_name`, or of the gamepad button if `button` is set.
+    pub key: String,
+    pub shift: bool,
+    pub glyph: Option<char>,
+    #[serde(default)]
+    pub button: bool,
+}
+
+fn default_difficulty() -> String
+{
+    return String::from("Normal");
+}
+
+fn default_starting_hearts() -> i32
+{
+    return MAX_HEARTS;
+}
+
+impl ReplayPress
+{
+    pub fn to_key_press(&self) -> Option<KeyPress>
+    {
+        let binding = if self.button
+        {
+            InputBinding::Button(gamepad_button_from_name(&self.key)?)
+        }
+        else
+        {
+            InputBinding::Key(KeyBinding { key_code: key_code_from_name(&self.key)?, shift: self.shift })
+        };
+
+        return Some(KeyPress { binding, glyph: self.glyph });
+    }
+}
+
+impl Replay
+{
+    /// Store this replay as the most recent one.
+    pub fn save_last(&self) -> Result<(), String>
+    {
+        let contents = ron::to_string(self).map_err(|err| err.to_string())?;
+        return storage::save(LAST_REPLAY_KEY, &contents);
+    }
+
+    pub fn load_last() -> Option<Self>
+    {
+        let contents = storage::load(LAST_REPLAY_KEY)?;
+        return ron::from_str(&contents).ok();
+    }
+}
+
+// =============================================
+// RECORD / PLAYBACK
+// =============================================
+
+/// Replay of the round in progress, if it is being recorded.
+#[derive(Resource, Default)]
+pub struct ReplayRecorder
+{
+    pub recording: Option<Replay>,
+}
+
+impl ReplayRecorder
+{
+    pub fn record(&mut self, time: f32, press: &KeyPress)
+    {
+        let Some(replay) = &mut self.recording else
+        {
+            return;
+        };
+
+        // Only keys and buttons that can be bound to a mole matter for playback.
+        let (key, shift, button) = match press.binding
+        {
+            InputBinding::Key(binding) => (format!("{:?}", binding.key_code), binding.shift, false),
+            InputBinding::Button(button) => (gamepad_button_name(button).map_or(String::new(), String::from), false, true),
+        };
+        let replay_press = ReplayPress { time, key, shift, glyph: press.glyph, button };
+        if replay_press.to_key_press().is_none()
+        {
+            return;
+        }
+
+        replay.presses.push(replay_press);
+    }
+
+    /// Stop recording and save what was recorded.
+    pub fn finish(&mut self)
+    {
+        if let Some(replay) = self.recording.take()
+        {
+            if let Err(err) = replay.save_last()
+            {
+                warn!("Could not save replay: {}", err);
+            }
+        }
+    }
+}
+
+/// Replay currently driving the round instead of the keyboard, if any.
+#[derive(Resource, Default)]
+pub struct ReplayPlayer
+{
+    pub playing: Option<Replay>,
+    next_idx: usize,
+}
+
+impl ReplayPlayer
+{
+    pub fn play(&mut self, replay: Replay)
+    {
+        self.playing = Some(replay);
+        self.next_idx = 0;
+    }
+
+    pub fn stop(&mut self)
+    {
+        self.playing = None;
+    }
+
+    pub fn is_playing(&self) -> bool
+    {
+        return self.playing.is_some();
+    }
+
+    /// Presses recorded at or before `time` that have not been returned yet.
+    pub fn presses_until(&mut self, time: f32) -> Vec<KeyPress>
+    {
+        let mut presses = Vec::new();
+        let Some(replay) = &self.playing else
+        {
+            return presses;
+        };
+
+        while let Some(press) = replay.presses.get(self.next_idx)
+        {
+            if press.time > time
+            {
+                break;
+            }
+
+            presses.extend(press.to_key_press());
+            self.next_idx += 1;
+        }
+
+        return presses;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn key(key_code: KeyCode, shift: bool, glyph: char) -> KeyPress
+    {
+        return KeyPress { binding: InputBinding::Key(KeyBinding { key_code, shift }), glyph: Some(glyph) };
+    }
+
+    fn recorded(presses: &[(f32, KeyPress)]) -> Replay
+    {
+        let mut recorder = ReplayRecorder { recording: Some(Replay { seed: 42, board: String::from("Classic"), ..default() }) };
+        for (time, press) in presses
+        {
+            recorder.record(*time, press);
+        }
+        return recorder.recording.unwrap();
+    }
+
+    #[test]
+    fn replays_survive_a_round_trip_through_ron()
+    {
+        let replay = recorded(&[(0.5, key(KeyCode::KeyA, false, 'A')),
+                                (1.25, key(KeyCode::Digit1, true, '!')),
+                                (2.0, KeyPress { binding: InputBinding::Button(GamepadButton::South), glyph: None })]);
+        let loaded: Replay = ron::from_str(&ron::to_string(&replay).unwrap()).unwrap();
+
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.board, "Classic");
+        let presses = loaded.presses.iter().map(|press| (press.time, press.to_key_press().unwrap().binding)).collect::<Vec<_>>();
+        assert_eq!(presses, vec![(0.5, InputBinding::Key(KeyBinding { key_code: KeyCode::KeyA, shift: false })),
+                                 (1.25, InputBinding::Key(KeyBinding { key_code: KeyCode::Digit1, shift: true })),
+                                 (2.0, InputBinding::Button(GamepadButton::South))]);
+    }
+
+    #[test]
+    fn keys_that_cannot_be_played_back_are_not_recorded()
+    {
+        let replay = recorded(&[(0.5, key(KeyCode::Escape, false, ' ')), (1.0, key(KeyCode::KeyS, false, 'S'))]);
+        assert_eq!(replay.presses.len(), 1);
+        assert_eq!(replay.presses[0].key, "KeyS");
+    }
+
+    #[test]
+    fn old_replays_get_defaults()
+    {
+        let replay: Replay = ron::from_str("(seed: 7, board: \"Classic\", layout: \"QWERTY\", input_mode: Physical, presses: [])").unwrap();
+        assert_eq!(replay.difficulty, "Normal");
+        assert_eq!(replay.starting_hearts, MAX_HEARTS);
+        assert_eq!(replay.mode, GameMode::default());
+    }
+
+    #[test]
+    fn playback_hands_out_each_press_once_it_is_due()
+    {
+        let mut player = ReplayPlayer::default();
+        player.play(recorded(&[(0.5, key(KeyCode::KeyA, false, 'A')), (1.0, key(KeyCode::KeyS, false, 'S'))]));
+
+        assert!(player.presses_until(0.25).is_empty());
+        assert_eq!(player.presses_until(0.5).len(), 1);
+        assert_eq!(player.presses_until(2.0).len(), 1);
+        assert!(player.presses_until(3.0).is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use rand::distributions::uniform::{SampleRange, SampleUniform};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Source of all gameplay randomness. Rounds started from the same seed play out
+/// the same, so seeds can be shared and bug reports reproduced.
+///
+/// ChaCha8 is used rather than `StdRng` because its output is stable across
+/// platforms and `rand` versions.
+#[derive(Resource)]
+pub struct GameRng
+{
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl GameRng
+{
+    pub fn from_seed(seed: u64) -> Self
+    {
+        Self
+        {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Seed the generator with a fresh random seed.
+    pub fn from_entropy() -> Self
+    {
+        return Self::from_seed(rand::thread_rng().gen());
+    }
+
+    /// Restart the sequence from `seed`.
+    pub fn reseed(&mut self, seed: u64)
+    {
+        *self = Self::from_seed(seed);
+    }
+
+    pub fn seed(&self) -> u64
+    {
+        return self.seed;
+    }
+
+    /// A generator for `stream` of the same seed, whose draws are independent of this one's
+    /// and of every other stream's.
+    pub fn stream(&self, stream: u64) -> Self
+    {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(stream);
+
+        return Self { seed: self.seed, rng };
+    }
+
+    pub fn gen_range<T, R>(&mut self, range: R) -> T
+        where T: SampleUniform, R: SampleRange<T>
+    {
+        return self.rng.gen_range(range);
+    }
+}
+
+impl Default for GameRng
+{
+    fn default() -> Self
+    {
+        return GameRng::from_entropy();
+    }
+}
@@ -0,0 +1,144 @@
+/// Consecutive hits needed to raise the multiplier by one.
+const COMBO_STEP: u32 = 5;
+const MAX_MULTIPLIER: i32 = 4;
+/// Points for each hit before bonuses.
+const HIT_POINTS: i32 = 1;
+/// Hits faster than this earn a speed bonus, more the faster they are.
+const SPEED_BONUS_WINDOW: f32 = 1.5;
+const MAX_SPEED_BONUS: i32 = 3;
+
+/// Points scored in a round, kept apart so the game over screen can break them down.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Scoring
+{
+    /// Hits in a row since the last mistake.
+    pub combo: u32,
+    pub best_combo: u32,
+    pub hit_points: i32,
+    /// Extra points from the combo multiplier.
+    pub combo_points: i32,
+    pub speed_points: i32,
+    /// Points from special moles, before the multiplier.
+    pub bonus_points: i32,
+}
+
+impl Scoring
+{
+    pub fn multiplier(&self) -> i32
+    {
+        return (1 + (self.combo / COMBO_STEP) as i32).min(MAX_MULTIPLIER);
+    }
+
+    /// A mole worth `bonus` extra points was hit `reaction_sec` after it came up. Returns the points
+    /// it was worth before the multiplier, and the multiplier they were scored at.
+    pub fn hit(&mut self, reaction_sec: f32, bonus: i32) -> (i32, i32)
+    {
+        let multiplier = self.multiplier();
+        let speed_bonus = ((SPEED_BONUS_WINDOW - reaction_sec) / SPEED_BONUS_WINDOW * MAX_SPEED_BONUS as f32).ceil().max(0.0) as i32;
+
+        self.hit_points += HIT_POINTS;
+        self.speed_points += speed_bonus * multiplier;
+        self.bonus_points += bonus * multiplier;
+        self.combo_points += HIT_POINTS * (multiplier - 1);
+
+        self.combo += 1;
+        self.best_combo = self.best_combo.max(self.combo);
+
+        return (HIT_POINTS + speed_bonus + bonus, multiplier);
+    }
+
+    /// A wrong key or an escaped mole breaks the combo.
+    pub fn miss(&mut self)
+    {
+        self.combo = 0;
+    }
+
+    pub fn total(&self) -> i32
+    {
+        return self.hit_points + self.combo_points + self.speed_points + self.bonus_points;
+    }
+
+    pub fn breakdown(&self) -> String
+    {
+        return format!("Hits {} + Combo {} + Speed {} + Bonus {}\n Best combo: {}",
+                        self.hit_points, self.combo_points, self.speed_points, self.bonus_points, self.best_combo);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Slow enough to earn no speed bonus.
+    const SLOW: f32 = SPEED_BONUS_WINDOW;
+
+    #[test]
+    fn multiplier_steps_up_every_combo_step()
+    {
+        let mut scoring = Scoring::default();
+        for _ in 0..COMBO_STEP
+        {
+            assert_eq!(scoring.hit(SLOW, 0), (HIT_POINTS, 1));
+        }
+        assert_eq!(scoring.hit(SLOW, 0), (HIT_POINTS, 2));
+        assert_eq!(scoring.combo, COMBO_STEP + 1);
+    }
+
+    #[test]
+    fn multiplier_is_capped()
+    {
+        let mut scoring = Scoring::default();
+        for _ in 0..COMBO_STEP * 10
+        {
+            scoring.hit(SLOW, 0);
+        }
+        assert_eq!(scoring.multiplier(), MAX_MULTIPLIER);
+        assert_eq!(scoring.hit(SLOW, 0), (HIT_POINTS, MAX_MULTIPLIER));
+    }
+
+    #[test]
+    fn miss_resets_the_combo()
+    {
+        let mut scoring = Scoring::default();
+        for _ in 0..COMBO_STEP * 2
+        {
+            scoring.hit(SLOW, 0);
+        }
+        scoring.miss();
+
+        assert_eq!(scoring.combo, 0);
+        assert_eq!(scoring.best_combo, COMBO_STEP * 2);
+        assert_eq!(scoring.multiplier(), 1);
+    }
+
+    #[test]
+    fn speed_bonus_is_largest_for_instant_hits()
+    {
+        let mut scoring = Scoring::default();
+        assert_eq!(scoring.hit(0.0, 0), (HIT_POINTS + MAX_SPEED_BONUS, 1));
+        assert_eq!(scoring.speed_points, MAX_SPEED_BONUS);
+    }
+
+    #[test]
+    fn no_speed_bonus_outside_the_window()
+    {
+        let mut scoring = Scoring::default();
+        assert_eq!(scoring.hit(SPEED_BONUS_WINDOW, 0), (HIT_POINTS, 1));
+        assert_eq!(scoring.hit(SPEED_BONUS_WINDOW * 2.0, 0), (HIT_POINTS, 1));
+        assert_eq!(scoring.speed_points, 0);
+    }
+
+    #[test]
+    fn total_counts_the_multiplier()
+    {
+        let mut scoring = Scoring::default();
+        for _ in 0..COMBO_STEP
+        {
+            scoring.hit(SLOW, 0);
+        }
+        scoring.hit(SLOW, 1);
+
+        assert_eq!(scoring.total(), COMBO_STEP as i32 * HIT_POINTS + (HIT_POINTS + 1) * 2);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::online::DEFAULT_RELAY_PORT;
+use crate::storage;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_KEY: &str = "settings.ron";
+
+/// Most hearts the healthbar has room for.
+pub const MAX_HEARTS: i32 = 10;
+
+/// Lowest and highest values the settings menu offers.
+const VOLUME_RANGE: (f64, f64) = (0.0, 1.0);
+const SHAKE_RANGE: (f32, f32) = (0.0, 2.0);
+const HEARTS_RANGE: (i32, i32) = (1, MAX_HEARTS);
+/// Lowest and highest hit rate the adaptive difficulty can aim for. Stepping below the lowest turns it off.
+const ADAPTIVE_TARGET_RANGE: (f32, f32) = (0.5, 0.95);
+
+/// Player preferences, saved between sessions.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings
+{
+    pub music_volume: f64,
+    pub sfx_volume: f64,
+    /// Multiplier on how far the screen moves when shaking.
+    pub shake_intensity: f32,
+    pub starting_hearts: i32,
+    /// Share of moles the adaptive difficulty aims to let the player hit, or `None` to use the selected curve.
+    pub adaptive_target: Option<f32>,
+    /// Address of the relay online races are played through.
+    pub relay_address: String,
+}
+
+impl Default for Settings
+{
+    fn default() -> Self
+    {
+        Self
+        {
+            music_volume: 0.2,
+            sfx_volume: 0.2,
+            shake_intensity: 1.0,
+            starting_hearts: MAX_HEARTS,
+            adaptive_target: None,
+            relay_address: format!("127.0.0.1:{}", DEFAULT_RELAY_PORT),
+        }
+    }
+}
+
+impl Settings
+{
+    pub fn load() -> Self
+    {
+        let mut settings: Self = storage::load(SETTINGS_KEY)
+                                    .and_then(|contents| ron::from_str(&contents).ok())
+                                    .unwrap_or_default();
+
+        // The file may have been edited by hand, so keep everything to what the menu allows.
+        settings.music_volume = settings.music_volume.clamp(VOLUME_RANGE.0, VOLUME_RANGE.1);
+        settings.sfx_volume = settings.sfx_volume.clamp(VOLUME_RANGE.0, VOLUME_RANGE.1);
+        settings.shake_intensity = settings.shake_intensity.clamp(SHAKE_RANGE.0, SHAKE_RANGE.1);
+        settings.starting_hearts = settings.starting_hearts.clamp(HEARTS_RANGE.0, HEARTS_RANGE.1);
+        settings.adaptive_target = settings.adaptive_target.map(|target| target.clamp(ADAPTIVE_TARGET_RANGE.0, ADAPTIVE_TARGET_RANGE.1));
+
+        return settings;
+    }
+
+    pub fn save(&self)
+    {
+        let result = ron::to_string(self).map_err(|err| err.to_string())
+                        .and_then(|contents| storage::save(SETTINGS_KEY, &contents));
+
+        if let Err(err) = result
+        {
+            warn!("Could not save settings: {}", err);
+        }
+    }
+}
+
+// =============================================
+// MENU
+// =============================================
+const MENU_ITEMS: [&str; 5] = ["Music volume", "SFX volume", "Screen shake", "Starting hearts", "Adaptive difficulty"];
+
+/// Which line of the settings menu is selected.
+#[derive(Resource, Default)]
+pub struct SettingsMenu
+{
+    selected: usize,
+}
+
+impl SettingsMenu
+{
+    pub fn move_selection(&mut self, down: bool)
+    {
+        let num_items = MENU_ITEMS.len();
+        self.selected = if down { (self.selected + 1) % num_items } else { (self.selected + num_items - 1) % num_items };
+    }
+
+    /// Nudge the selected setting up or down by one step.
+    pub fn adjust(&self, settings: &mut Settings, up: bool)
+    {
+        let step = if up { 1.0 } else { -1.0 };
+        match self.selected
+        {
+            0 => settings.music_volume = (settings.music_volume + step * 0.05).clamp(VOLUME_RANGE.0, VOLUME_RANGE.1),
+            1 => settings.sfx_volume = (settings.sfx_volume + step * 0.05).clamp(VOLUME_RANGE.0, VOLUME_RANGE.1),
+            2 => settings.shake_intensity = (settings.shake_intensity + step as f32 * 0.25).clamp(SHAKE_RANGE.0, SHAKE_RANGE.1),
+            3 => settings.starting_hearts = (settings.starting_hearts + step as i32).clamp(HEARTS_RANGE.0, HEARTS_RANGE.1),
+            _ =>
+            {
+                let (min, max) = ADAPTIVE_TARGET_RANGE;
+                settings.adaptive_target = match settings.adaptive_target
+                {
+                    None if up => Some(min),
+                    None => None,
+                    Some(target) if !up && target <= min => None,
+                    Some(target) => Some((target + step as f32 * 0.05).clamp(min, max)),
+                };
+            }
+        }
+    }
+
+    pub fn describe(&self, settings: &Settings) -> String
+    {
+        let values = [format!("{:.0}%", settings.music_volume * 100.0),
+                      format!("{:.0}%", settings.sfx_volume * 100.0),
+                      format!("{:.0}%", settings.shake_intensity * 100.0),
+                      format!("{}", settings.starting_hearts),
+                      settings.adaptive_target.map_or(String::from("Off"), |target| format!("{:.0}% hits", target * 100.0))];
+
+        let mut menu_str = String::from("SETTINGS\n");
+        for (idx, (item, value)) in MENU_ITEMS.iter().zip(values.iter()).enumerate()
+        {
+            let cursor = if idx == self.selected { ">" } else { " " };
+            menu_str += &format!("\n{} {}: {}", cursor, item, value);
+        }
+
+        return menu_str;
+    }
+}
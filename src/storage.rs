@@ -0,0 +1,65 @@
+// Small key-value store for things that should outlive a session (replays, scores, settings).
+// Native builds keep one file per key in the platform data directory, the web build uses
+// the browser's localStorage.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform
+{
+    use std::path::PathBuf;
+
+    const APP_DIR: &str = "WhackAKey";
+
+    fn data_dir() -> Option<PathBuf>
+    {
+        let var = |name: &str| std::env::var_os(name).map(PathBuf::from);
+
+        let base = if cfg!(target_os = "windows")
+        {
+            var("APPDATA")
+        }
+        else if cfg!(target_os = "macos")
+        {
+            var("HOME").map(|home| home.join("Library").join("Application Support"))
+        }
+        else
+        {
+            var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local").join("share")))
+        };
+
+        return base.map(|dir| dir.join(APP_DIR));
+    }
+
+    pub fn save(key: &str, contents: &str) -> Result<(), String>
+    {
+        let dir = data_dir().ok_or("No data directory found")?;
+        std::fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+        return std::fs::write(dir.join(key), contents).map_err(|err| err.to_string());
+    }
+
+    pub fn load(key: &str) -> Option<String>
+    {
+        return std::fs::read_to_string(data_dir()?.join(key)).ok();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform
+{
+    fn local_storage() -> Option<web_sys::Storage>
+    {
+        return web_sys::window()?.local_storage().ok()?;
+    }
+
+    pub fn save(key: &str, contents: &str) -> Result<(), String>
+    {
+        let storage = local_storage().ok_or("localStorage is not available")?;
+        return storage.set_item(key, contents).map_err(|err| format!("{:?}", err));
+    }
+
+    pub fn load(key: &str) -> Option<String>
+    {
+        return local_storage()?.get_item(key).ok()?;
+    }
+}
+
+pub use platform::{load, save};
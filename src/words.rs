@@ -0,0 +1,94 @@
+use crate::keyboard::to_upper_glyph;
+use crate::rng::GameRng;
+use std::collections::HashSet;
+
+/// Word list for Word mode, built into the binary so the web build has it without a fetch.
+const WORD_LIST: &str = include_str!("../assets/words/common.txt");
+
+/// Bonus points for finishing a word, per letter in it.
+pub const WORD_BONUS_PER_LETTER: i32 = 1;
+
+/// A word being spelled out by the moles.
+#[derive(Clone, Debug)]
+pub struct WordProgress
+{
+    letters: Vec<char>,
+    /// Letters hit so far.
+    typed: usize,
+}
+
+impl WordProgress
+{
+    /// The letter whose mole should be up next, or `None` once the word is done.
+    pub fn current(&self) -> Option<char>
+    {
+        return self.letters.get(self.typed).copied();
+    }
+
+    pub fn advance(&mut self)
+    {
+        self.typed += 1;
+    }
+
+    pub fn is_finished(&self) -> bool
+    {
+        return self.typed >= self.letters.len();
+    }
+
+    pub fn bonus(&self) -> i32
+    {
+        return self.letters.len() as i32 * WORD_BONUS_PER_LETTER;
+    }
+
+    /// The word with the letter to hit next in brackets.
+    pub fn display(&self) -> String
+    {
+        let mut word = String::new();
+        for (idx, letter) in self.letters.iter().enumerate()
+        {
+            if idx == self.typed { word += &format!("[{}]", letter); } else { word.push(*letter); }
+        }
+        return word;
+    }
+}
+
+fn words() -> impl Iterator<Item = &'static str>
+{
+    return WORD_LIST.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'));
+}
+
+/// Words that can be spelled with the `available` glyphs. A board that can't spell any
+/// (like the numpad) gets its glyphs one at a time instead, so the round still plays.
+pub fn spellable_words(available: &HashSet<char>) -> Vec<String>
+{
+    let fits = |word: &&str| word.chars().all(|c| available.contains(&to_upper_glyph(c)));
+
+    let spellable: Vec<String> = words().filter(fits).map(String::from).collect();
+    if !spellable.is_empty()
+    {
+        return spellable;
+    }
+
+    // Sorted, so the same seed picks the same letters.
+    let mut glyphs: Vec<char> = available.iter().copied().collect();
+    glyphs.sort();
+    return glyphs.into_iter().map(String::from).collect();
+}
+
+/// Pick a random word from `words`.
+pub fn pick_word(rng: &mut GameRng, words: &[String]) -> Option<WordProgress>
+{
+    if words.is_empty()
+    {
+        return None;
+    }
+
+    let word = &words[rng.gen_range(0..words.len())];
+    return Some(WordProgress
+    {
+        letters: word.chars().map(to_upper_glyph).collect(),
+        typed: 0,
+    });
+}